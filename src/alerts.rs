@@ -0,0 +1,331 @@
+//! Alert rules over chain follower observations, reported to the log and to webhooks.
+
+use crate::follower::{ChainFollower, FollowerSink, Observation, DEFAULT_WINDOW};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Rule {
+    /// Red share of the last `blocks` selected chain blocks' mergesets above `threshold`.
+    RedRatio { threshold: f64, blocks: usize },
+    /// More than `max` tips near the head.
+    TipCount { max: usize },
+    /// More than `max` selected chain blocks replaced between two polls.
+    ReorgDepth { max: u64 },
+    /// No new selected tip for `seconds`.
+    Stalled { seconds: u64 },
+}
+
+impl Rule {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Rule::RedRatio { .. } => "red_ratio",
+            Rule::TipCount { .. } => "tip_count",
+            Rule::ReorgDepth { .. } => "reorg_depth",
+            Rule::Stalled { .. } => "stalled",
+        }
+    }
+
+    /// What is wrong, if `obs` breaks the rule.
+    pub fn check(&self, obs: &Observation) -> Option<String> {
+        match *self {
+            Rule::RedRatio { threshold, blocks } => {
+                let recent = &obs.chain[obs.chain.len().saturating_sub(blocks)..];
+                let reds: usize = recent.iter().map(|b| b.mergeset_reds).sum();
+                let total: usize = recent
+                    .iter()
+                    .map(|b| b.mergeset_blues + b.mergeset_reds)
+                    .sum();
+                let ratio = reds as f64 / total.max(1) as f64;
+                (ratio > threshold).then(|| {
+                    format!(
+                        "red ratio {:.3} over the last {} chain blocks exceeds {}",
+                        ratio,
+                        recent.len(),
+                        threshold
+                    )
+                })
+            }
+            Rule::TipCount { max } => {
+                (obs.tip_count > max).then(|| format!("{} tips exceed {}", obs.tip_count, max))
+            }
+            Rule::ReorgDepth { max } => (obs.reorg_depth > max).then(|| {
+                format!(
+                    "reorg replaced {} chain blocks, more than {}",
+                    obs.reorg_depth, max
+                )
+            }),
+            Rule::Stalled { seconds } => stalled(seconds, obs.since_new_block),
+        }
+    }
+}
+
+fn stalled(seconds: u64, since_new_block: Duration) -> Option<String> {
+    (since_new_block > Duration::from_secs(seconds))
+        .then(|| format!("no new block for {}s", since_new_block.as_secs()))
+}
+
+/// Alerting setup, read from a JSON file.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AlertConfig {
+    pub rules: Vec<Rule>,
+    /// URLs every alert is posted to as JSON.
+    #[serde(default)]
+    pub webhooks: Vec<String>,
+}
+
+impl AlertConfig {
+    pub fn from_file(path: &Path) -> anyhow::Result<Self> {
+        Ok(serde_json::from_slice(&std::fs::read(path)?)?)
+    }
+
+    /// Selected chain blocks the follower must keep to evaluate every rule.
+    pub fn window(&self) -> u64 {
+        self.rules
+            .iter()
+            .filter_map(|rule| match rule {
+                Rule::RedRatio { blocks, .. } => Some(*blocks as u64),
+                _ => None,
+            })
+            .fold(DEFAULT_WINDOW, u64::max)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Alert {
+    pub rule: &'static str,
+    pub message: String,
+    pub tip_number: Option<u64>,
+    /// Milliseconds since the Unix epoch.
+    pub fired_at: u64,
+}
+
+// A webhook that does not answer in this time is given up on.
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub struct AlertEngine {
+    config: AlertConfig,
+    client: reqwest::Client,
+    // Whether each rule was broken at the previous evaluation.
+    firing: Vec<bool>,
+}
+
+impl AlertEngine {
+    pub fn new(config: AlertConfig) -> Self {
+        let firing = vec![false; config.rules.len()];
+        Self {
+            config,
+            client: reqwest::Client::builder()
+                .timeout(WEBHOOK_TIMEOUT)
+                .build()
+                .expect("failed to build the webhook client"),
+            firing,
+        }
+    }
+
+    /// Alerts for the rules that `obs` breaks and the previous observation did not,
+    /// so a lasting condition fires once until it clears.
+    pub fn evaluate(&mut self, obs: &Observation) -> Vec<Alert> {
+        self.fire(obs.chain.last().map(|b| b.number), |rule| {
+            Some(rule.check(obs))
+        })
+    }
+
+    /// Alerts for a poll that failed with `error`. Only `Stalled` rules can be
+    /// judged without an observation; the others keep their state until the
+    /// next successful poll.
+    pub fn evaluate_unreachable(
+        &mut self,
+        since_new_block: Duration,
+        tip_number: Option<u64>,
+        error: &anyhow::Error,
+    ) -> Vec<Alert> {
+        self.fire(tip_number, |rule| match *rule {
+            Rule::Stalled { seconds } => Some(
+                stalled(seconds, since_new_block)
+                    .map(|message| format!("{} (chain unreachable: {:#})", message, error)),
+            ),
+            _ => None,
+        })
+    }
+
+    // Fires the rules whose check returns a message and did not at the previous
+    // evaluation. Rules the check skips with `None` keep their state.
+    fn fire(
+        &mut self,
+        tip_number: Option<u64>,
+        check: impl Fn(&Rule) -> Option<Option<String>>,
+    ) -> Vec<Alert> {
+        let fired_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as u64);
+        let mut alerts = Vec::new();
+        for (rule, firing) in self.config.rules.iter().zip(self.firing.iter_mut()) {
+            let Some(message) = check(rule) else {
+                continue;
+            };
+            if let (Some(message), false) = (&message, *firing) {
+                alerts.push(Alert {
+                    rule: rule.name(),
+                    message: message.clone(),
+                    tip_number,
+                    fired_at,
+                });
+            }
+            *firing = message.is_some();
+        }
+        alerts
+    }
+
+    /// Logs `alert` and posts it to every webhook in the background, so a slow
+    /// target does not hold up the follower. Webhook failures are logged rather
+    /// than returned so one bad target does not silence the others.
+    pub fn notify(&self, alert: &Alert) {
+        eprintln!("alert [{}]: {}", alert.rule, alert.message);
+        for url in &self.config.webhooks {
+            let post = self.client.post(url).json(alert).send();
+            let url = url.clone();
+            tokio::spawn(async move {
+                if let Err(e) = post.await.and_then(|resp| resp.error_for_status()) {
+                    eprintln!("failed to post alert to {}: {}", url, e);
+                }
+            });
+        }
+    }
+}
+
+#[async_trait]
+impl FollowerSink for AlertEngine {
+    async fn observe(&mut self, obs: &Observation) {
+        for alert in self.evaluate(obs) {
+            self.notify(&alert);
+        }
+    }
+
+    async fn poll_failed(&mut self, follower: &ChainFollower, error: &anyhow::Error) {
+        let alerts =
+            self.evaluate_unreachable(follower.since_new_block(), follower.tip_number(), error);
+        for alert in alerts {
+            self.notify(&alert);
+        }
+    }
+}
+
+#[test]
+fn test_rules_fire_once_until_cleared() {
+    use crate::chain_reader::ext::ChainBlock;
+    use starcoin_crypto::HashValue;
+
+    let config: AlertConfig = serde_json::from_str(
+        r#"{"rules": [
+            {"kind": "red_ratio", "threshold": 0.1, "blocks": 2},
+            {"kind": "reorg_depth", "max": 0},
+            {"kind": "stalled", "seconds": 60}
+        ]}"#,
+    )
+    .unwrap();
+    assert_eq!(config.window(), DEFAULT_WINDOW);
+    let mut engine = AlertEngine::new(config);
+
+    let block = |number, reds| ChainBlock {
+        id: HashValue::random(),
+        number,
+        timestamp: 0,
+        blue_score: None,
+        mergeset_blues: 2,
+        mergeset_reds: reds,
+        time_delta: None,
+    };
+    let mut obs = Observation {
+        chain: vec![block(1, 5), block(2, 0), block(3, 1)],
+        tip_count: 1,
+        reorg_depth: 2,
+        since_new_block: Duration::from_secs(1),
+        stats: Default::default(),
+        blocks: Vec::new(),
+    };
+    let rules: Vec<_> = engine.evaluate(&obs).iter().map(|a| a.rule).collect();
+    assert_eq!(rules, vec!["red_ratio", "reorg_depth"]);
+    assert!(engine.evaluate(&obs).is_empty());
+
+    obs.reorg_depth = 0;
+    assert!(engine.evaluate(&obs).is_empty());
+    obs.reorg_depth = 1;
+    let rules: Vec<_> = engine.evaluate(&obs).iter().map(|a| a.rule).collect();
+    assert_eq!(rules, vec!["reorg_depth"]);
+
+    // An unreachable node still stalls, while the other rules stay as they were.
+    let error = anyhow::anyhow!("connection refused");
+    let alerts = engine.evaluate_unreachable(Duration::from_secs(90), Some(3), &error);
+    assert_eq!(alerts.len(), 1);
+    assert_eq!((alerts[0].rule, alerts[0].tip_number), ("stalled", Some(3)));
+    assert!(alerts[0].message.contains("connection refused"));
+    obs.since_new_block = Duration::from_secs(1);
+    assert!(engine.evaluate(&obs).is_empty());
+}
+
+#[tokio::test]
+async fn test_tip_count_fires_on_parallel_tips() -> anyhow::Result<()> {
+    use crate::chain_reader::mock::MockChainReader;
+    use std::sync::Arc;
+
+    let config: AlertConfig =
+        serde_json::from_str(r#"{"rules": [{"kind": "tip_count", "max": 1}]}"#)?;
+    let mut follower = ChainFollower::new(Arc::new(MockChainReader::new()), config.window());
+    let mut engine = AlertEngine::new(config);
+
+    let alerts = engine.evaluate(&follower.poll().await?);
+    assert_eq!(alerts.len(), 1);
+    assert_eq!(alerts[0].rule, "tip_count");
+    assert_eq!(alerts[0].message, "2 tips exceed 1");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_alert_posted_to_webhook() -> anyhow::Result<()> {
+    use actix_web::{post, web, App, HttpResponse, HttpServer};
+    use tokio::sync::mpsc;
+
+    #[post("/hook")]
+    async fn hook(
+        tx: web::Data<mpsc::UnboundedSender<serde_json::Value>>,
+        body: web::Json<serde_json::Value>,
+    ) -> HttpResponse {
+        let _ = tx.send(body.into_inner());
+        HttpResponse::Ok().finish()
+    }
+
+    let (tx, mut rx) = mpsc::unbounded_channel::<serde_json::Value>();
+    let server = HttpServer::new(move || {
+        App::new()
+            .app_data(web::Data::new(tx.clone()))
+            .service(hook)
+    })
+    .workers(1)
+    .bind(("127.0.0.1", 0))?;
+    let addr = server.addrs()[0];
+    let server = server.run();
+    let handle = server.handle();
+    tokio::spawn(server);
+
+    let engine = AlertEngine::new(AlertConfig {
+        rules: Vec::new(),
+        webhooks: vec![format!("http://{}/hook", addr)],
+    });
+    let alert = Alert {
+        rule: "stalled",
+        message: "no new block for 90s".to_string(),
+        tip_number: Some(7),
+        fired_at: 0,
+    };
+    engine.notify(&alert);
+
+    let received = rx.recv().await.expect("webhook received the alert");
+    assert_eq!(received["rule"], "stalled");
+    assert_eq!(received["tip_number"], 7);
+    handle.stop(true).await;
+    Ok(())
+}
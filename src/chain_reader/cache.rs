@@ -0,0 +1,206 @@
+use crate::chain_reader::ext::{BlockBody, BlockHeader, ChainReaderExt, GhostdagData};
+use crate::metrics::Metrics;
+use async_trait::async_trait;
+use starcoin_crypto::HashValue;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::{Arc, RwLock};
+
+/// Default number of headers kept by `CachedChainReader`.
+pub const DEFAULT_CAPACITY: usize = 200_000;
+
+#[derive(Default)]
+struct HeaderIndex {
+    headers: HashMap<HashValue, BlockHeader>,
+    // Sorted hashes, so a hex prefix is a contiguous range.
+    ids: BTreeSet<HashValue>,
+    by_number: BTreeMap<u64, Vec<HashValue>>,
+}
+
+impl HeaderIndex {
+    fn insert(&mut self, header: &BlockHeader, capacity: usize) {
+        if self.headers.contains_key(&header.id) {
+            return;
+        }
+        self.headers.insert(header.id, header.clone());
+        self.ids.insert(header.id);
+        self.by_number.entry(header.number).or_default().push(header.id);
+        // Headers are immutable, so only the oldest ever need to go.
+        while self.headers.len() > capacity {
+            let Some((_, ids)) = self.by_number.pop_first() else {
+                break;
+            };
+            for id in ids {
+                self.headers.remove(&id);
+                self.ids.remove(&id);
+            }
+        }
+    }
+}
+
+/// Keeps every header the wrapped reader returns, up to `capacity` with the
+/// lowest numbers evicted first. Serves repeated header lookups locally and
+/// answers prefix and number lookups over the blocks seen so far.
+pub struct CachedChainReader<R> {
+    inner: R,
+    capacity: usize,
+    index: RwLock<HeaderIndex>,
+    metrics: Option<Arc<Metrics>>,
+}
+
+impl<R: ChainReaderExt> CachedChainReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self::with_capacity(inner, DEFAULT_CAPACITY)
+    }
+
+    pub fn with_capacity(inner: R, capacity: usize) -> Self {
+        Self {
+            inner,
+            capacity,
+            index: RwLock::new(HeaderIndex::default()),
+            metrics: None,
+        }
+    }
+
+    /// Count header lookups served locally and fetched upstream.
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.read().unwrap().headers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn record(&self, headers: &[BlockHeader]) {
+        let mut index = self.index.write().unwrap();
+        for header in headers {
+            index.insert(header, self.capacity);
+        }
+    }
+}
+
+#[async_trait]
+impl<R: ChainReaderExt> ChainReaderExt for CachedChainReader<R> {
+    async fn get_selected_chain(
+        &self,
+        number: Option<u64>,
+        count: u64,
+    ) -> anyhow::Result<Vec<BlockHeader>> {
+        let chain = self.inner.get_selected_chain(number, count).await?;
+        self.record(&chain);
+        Ok(chain)
+    }
+
+    async fn get_ghostdag_data(
+        &self,
+        ids: &[HashValue],
+    ) -> anyhow::Result<Vec<Option<GhostdagData>>> {
+        self.inner.get_ghostdag_data(ids).await
+    }
+
+    async fn get_headers(&self, ids: &[HashValue]) -> anyhow::Result<Vec<BlockHeader>> {
+        let (mut headers, misses) = {
+            let index = self.index.read().unwrap();
+            let mut hits = Vec::new();
+            let mut misses = Vec::new();
+            for id in ids {
+                match index.headers.get(id) {
+                    Some(header) => hits.push(header.clone()),
+                    None => misses.push(*id),
+                }
+            }
+            (hits, misses)
+        };
+        if let Some(metrics) = &self.metrics {
+            metrics.record_cache_lookup(headers.len(), misses.len());
+        }
+        if !misses.is_empty() {
+            let fetched = self.inner.get_headers(&misses).await?;
+            self.record(&fetched);
+            headers.extend(fetched);
+        }
+        Ok(headers)
+    }
+
+    async fn get_header_with_raw(
+        &self,
+        id: HashValue,
+    ) -> anyhow::Result<Option<(BlockHeader, Option<serde_json::Value>)>> {
+        let found = self.inner.get_header_with_raw(id).await?;
+        if let Some((header, _)) = &found {
+            self.record(std::slice::from_ref(header));
+        }
+        Ok(found)
+    }
+
+    async fn get_block_bodies(&self, ids: &[HashValue]) -> anyhow::Result<Vec<Option<BlockBody>>> {
+        self.inner.get_block_bodies(ids).await
+    }
+
+    async fn get_dag_tips(&self) -> anyhow::Result<Vec<HashValue>> {
+        self.inner.get_dag_tips().await
+    }
+
+    async fn find_by_prefix(&self, prefix: &str) -> anyhow::Result<Vec<BlockHeader>> {
+        let pad = |fill: char| {
+            let hex: String = prefix.chars().chain(std::iter::repeat(fill)).take(64).collect();
+            HashValue::from_hex(hex)
+        };
+        let (low, high) = (pad('0')?, pad('f')?);
+        let index = self.index.read().unwrap();
+        Ok(index
+            .ids
+            .range(low..=high)
+            .map(|id| index.headers[id].clone())
+            .collect())
+    }
+
+    async fn find_by_number(&self, number: u64) -> anyhow::Result<Vec<BlockHeader>> {
+        let index = self.index.read().unwrap();
+        Ok(index
+            .by_number
+            .get(&number)
+            .into_iter()
+            .flatten()
+            .map(|id| index.headers[id].clone())
+            .collect())
+    }
+}
+
+#[tokio::test]
+async fn test_cache_search() -> anyhow::Result<()> {
+    use crate::chain_reader::mock::{mock_view, MockChainReader};
+    use crate::chain_reader::{BlockQuery, ChainReader};
+
+    let reader = CachedChainReader::new(MockChainReader::new());
+    assert!(reader.search(&"5".parse()?).await?.is_empty());
+
+    let view = mock_view(&reader).await?;
+    assert_eq!(reader.len(), view.dag_nodes().len());
+    let side = view.dag_nodes().into_iter().find(|n| n.score == 5).unwrap();
+
+    let by_number = reader.search(&"5".parse()?).await?;
+    assert_eq!(by_number.iter().map(|h| h.id).collect::<Vec<_>>(), vec![side.id]);
+    let query: BlockQuery = side.id.to_hex_literal()[..10].parse()?;
+    assert!(reader.search(&query).await?.iter().any(|h| h.id == side.id));
+    let query: BlockQuery = side.id.to_hex().parse()?;
+    assert_eq!(reader.search(&query).await?.len(), 1);
+    assert!("0x1".parse::<BlockQuery>().is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_cache_evicts_oldest() -> anyhow::Result<()> {
+    use crate::chain_reader::mock::{mock_view, MockChainReader};
+
+    let reader = CachedChainReader::with_capacity(MockChainReader::new(), 3);
+    mock_view(&reader).await?;
+    assert_eq!(reader.len(), 3);
+    assert!(reader.find_by_number(0).await?.is_empty());
+    assert_eq!(reader.find_by_number(7).await?.len(), 1);
+    Ok(())
+}
@@ -1,10 +1,13 @@
 use crate::chain_reader::ChainReader;
-use crate::dag_graph::{DagEdge, DagGraphProvider, DagNode, NodeColor};
+use crate::dag_graph::{DagEdge, DagGraphProvider, DagNode, DagStats, NodeColor, VirtualBlock};
 use async_trait::async_trait;
+use serde::{Serialize, Serializer};
 use starcoin_crypto::HashValue;
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap, HashMap, HashSet, VecDeque};
+use std::sync::OnceLock;
 
-use super::BlockWindow;
+use super::{BlockQuery, BlockWindow, CrawlLimits};
 
 #[async_trait]
 pub trait ChainReaderExt: Send + Sync {
@@ -21,20 +24,100 @@ pub trait ChainReaderExt: Send + Sync {
     ) -> anyhow::Result<Vec<Option<GhostdagData>>>;
 
     async fn get_headers(&self, ids: &[HashValue]) -> anyhow::Result<Vec<BlockHeader>>;
+
+    /// Header of `id` along with every header field the node reports, for display.
+    /// Readers that only know `BlockHeader`'s fields return `None` for the latter.
+    async fn get_header_with_raw(
+        &self,
+        id: HashValue,
+    ) -> anyhow::Result<Option<(BlockHeader, Option<serde_json::Value>)>> {
+        let headers = self.get_headers(&[id]).await?;
+        Ok(headers.into_iter().find(|h| h.id == id).map(|h| (h, None)))
+    }
+
+    /// Bodies of `ids`, in order, or `None` where a body could not be fetched.
+    /// Readers that cannot fetch bodies return `None` for every block.
+    async fn get_block_bodies(&self, ids: &[HashValue]) -> anyhow::Result<Vec<Option<BlockBody>>> {
+        Ok(vec![None; ids.len()])
+    }
+
+    /// Known blocks whose hash starts with the lowercase hex `prefix`. Readers
+    /// without a local index know none.
+    /// Blocks without children the node currently knows of. Readers that cannot
+    /// ask the node report the selected tip alone.
+    async fn get_dag_tips(&self) -> anyhow::Result<Vec<HashValue>> {
+        let tip = self.get_selected_chain(None, 1).await?;
+        Ok(tip.into_iter().map(|h| h.id).collect())
+    }
+
+    async fn find_by_prefix(&self, _prefix: &str) -> anyhow::Result<Vec<BlockHeader>> {
+        Ok(Vec::new())
+    }
+
+    /// Known blocks with `number`, on or off the selected chain.
+    async fn find_by_number(&self, _number: u64) -> anyhow::Result<Vec<BlockHeader>> {
+        Ok(Vec::new())
+    }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
 pub struct GhostdagData {
     pub blue_score: u64,
+    /// Serialized as a decimal string, since it may not fit in a JSON number.
+    #[serde(serialize_with = "serialize_decimal")]
+    pub blue_work: u128,
     pub selected_parent: HashValue,
     pub mergeset_blues: Vec<HashValue>,
     pub mergeset_reds: Vec<HashValue>,
+    /// Size of each blue block's anticone within the blue set.
+    pub blues_anticone_sizes: BTreeMap<HashValue, u64>,
 }
 
-#[derive(Clone, Debug)]
+fn serialize_decimal<S: Serializer>(value: &u128, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(value)
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct BlockBody {
+    pub transactions: Vec<HashValue>,
+    pub gas_used: u64,
+}
+
+/// A selected chain block with a summary of what it merged.
+#[derive(Clone, Debug, Serialize)]
+pub struct ChainBlock {
+    pub id: HashValue,
+    pub number: u64,
+    /// Milliseconds since the Unix epoch.
+    pub timestamp: u64,
+    pub blue_score: Option<u64>,
+    pub mergeset_blues: usize,
+    pub mergeset_reds: usize,
+    /// Milliseconds since the previous selected chain block, if that block exists.
+    pub time_delta: Option<u64>,
+}
+
+/// What the chain says about one block, independent of any view.
+#[derive(Clone, Debug, Serialize)]
+pub struct BlockDetail {
+    pub id: HashValue,
+    pub number: u64,
+    /// Milliseconds since the Unix epoch.
+    pub timestamp: u64,
+    pub parents: Vec<HashValue>,
+    /// All header fields as reported by the node, if the reader exposes them.
+    pub header: Option<serde_json::Value>,
+    pub ghostdag: Option<GhostdagData>,
+    /// Whether the block is on the node's current selected chain.
+    pub is_selected_chain: bool,
+}
+
+#[derive(Clone, Debug, Serialize)]
 pub struct BlockHeader {
     pub id: HashValue,
     pub number: u64,
+    /// Milliseconds since the Unix epoch.
+    pub timestamp: u64,
     pub parents_hash: Vec<HashValue>,
 }
 
@@ -52,49 +135,193 @@ impl BlockHeader {
     }
 }
 
+#[derive(Default)]
 pub struct DagBuildContext {
     pub header_map: HashMap<HashValue, BlockHeader>,
     pub ghostdag_map: HashMap<HashValue, GhostdagData>,
+    /// Requested blocks the reader could not return.
+    pub missing: Vec<HashValue>,
+    /// Set when the crawl stopped early because the node budget ran out.
+    pub truncated: bool,
+    /// Ghostdag data of blocks off the selected chain. Kept apart from
+    /// `ghostdag_map` because only selected chain mergesets decide node colors.
+    pub side_ghostdag: HashMap<HashValue, GhostdagData>,
+    /// The node's tips, when the view ends at the selected tip.
+    pub node_tips: Option<Vec<HashValue>>,
+    // `canonical_order`, computed on first use.
+    pub(crate) order: OnceLock<Vec<HashValue>>,
+}
+
+impl DagBuildContext {
+    /// Ghostdag data of any block in the view, on or off the selected chain.
+    pub fn ghostdag(&self, id: &HashValue) -> Option<&GhostdagData> {
+        self.ghostdag_map
+            .get(id)
+            .or_else(|| self.side_ghostdag.get(id))
+    }
 }
 
 impl DagGraphProvider for DagBuildContext {
     fn dag_nodes(&self) -> Vec<DagNode> {
-        let mut color_map = HashMap::new();
-        for gd in self.ghostdag_map.values() {
-            for &b in &gd.mergeset_blues {
-                color_map.insert(b, NodeColor::Blue);
-            }
-            for &r in &gd.mergeset_reds {
-                color_map.insert(r, NodeColor::Red);
+        let mut merge_map = HashMap::new();
+        for (chain_id, gd) in &self.ghostdag_map {
+            let blues = gd.mergeset_blues.iter().map(|b| (b, NodeColor::Blue));
+            let reds = gd.mergeset_reds.iter().map(|r| (r, NodeColor::Red));
+            for (index, (&id, color)) in blues.chain(reds).enumerate() {
+                merge_map.insert(id, (color, *chain_id, index));
             }
         }
 
-        self.header_map
+        self.canonical_order()
             .iter()
+            .map(|&id| (id, &self.header_map[&id]))
             .map(|(id, header)| {
-                let color = color_map.get(id).copied().unwrap_or(NodeColor::Unknown);
+                let merged = merge_map.get(&id).copied();
+                let omitted_parents = header
+                    .parents_hash
+                    .iter()
+                    .filter(|p| !self.header_map.contains_key(p))
+                    .count();
                 DagNode {
-                    id: *id,
+                    id,
                     score: header.number(),
-                    color,
+                    blue_score: self.ghostdag(&id).map(|gd| gd.blue_score),
+                    timestamp: header.timestamp,
+                    color: merged.map_or(NodeColor::Unknown, |(color, _, _)| color),
+                    merged_by: merged.map(|(_, by, _)| by),
+                    mergeset_index: merged.map(|(_, _, index)| index),
+                    is_boundary: omitted_parents > 0,
+                    omitted_parents,
+                    txn_count: None,
+                    gas_used: None,
+                    position: None,
                 }
             })
             .collect()
     }
     fn dag_edges(&self) -> Vec<DagEdge> {
-        let mut edges = Vec::new();
+        self.parent_edges(false)
+    }
+
+    fn dag_stub_edges(&self) -> Vec<DagEdge> {
+        self.parent_edges(true)
+    }
+
+    fn missing_blocks(&self) -> Vec<HashValue> {
+        let mut missing = self.missing.clone();
+        missing.sort();
+        missing
+    }
 
+    fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    fn dag_tips(&self) -> Vec<HashValue> {
+        self.tips()
+    }
+
+    fn virtual_block(&self) -> Option<VirtualBlock> {
+        DagBuildContext::virtual_block(self)
+    }
+
+    fn chain_blocks(&self) -> Vec<ChainBlock> {
+        let mut chain: Vec<_> = self
+            .ghostdag_map
+            .iter()
+            .filter_map(|(id, gd)| Some((self.header_map.get(id)?, gd)))
+            .collect();
+        chain.sort_by_key(|(header, _)| (header.number, header.id));
+        chain
+            .iter()
+            .enumerate()
+            .map(|(i, (header, gd))| ChainBlock {
+                id: header.id,
+                number: header.number,
+                timestamp: header.timestamp,
+                blue_score: Some(gd.blue_score),
+                mergeset_blues: gd.mergeset_blues.len(),
+                mergeset_reds: gd.mergeset_reds.len(),
+                time_delta: i
+                    .checked_sub(1)
+                    .map(|prev| header.timestamp.saturating_sub(chain[prev].0.timestamp)),
+            })
+            .collect()
+    }
+
+    fn dag_stats(&self) -> DagStats {
+        self.stats()
+    }
+}
+
+impl DagBuildContext {
+    /// Blocks of the view in topological order, parents before children.
+    ///
+    /// Among blocks whose parents have all been emitted, the one with the lowest
+    /// blue work comes first, then the lowest number, then the lowest hash. Blocks
+    /// without ghostdag data sort before those with it. Computed once per view.
+    pub fn canonical_order(&self) -> &[HashValue] {
+        self.order.get_or_init(|| self.topological_order())
+    }
+
+    fn topological_order(&self) -> Vec<HashValue> {
+        let key = |id: &HashValue| {
+            let work = self.ghostdag(id).map(|gd| gd.blue_work);
+            Reverse((work, self.header_map[id].number, *id))
+        };
+        let mut pending: HashMap<HashValue, usize> = HashMap::new();
+        let mut children: HashMap<HashValue, Vec<HashValue>> = HashMap::new();
         for (id, header) in &self.header_map {
-            let selected_parent = self.ghostdag_map.get(id).map(|gd| gd.selected_parent);
+            let parents: HashSet<_> = header
+                .parents_hash
+                .iter()
+                .filter(|p| self.header_map.contains_key(p))
+                .collect();
+            pending.insert(*id, parents.len());
+            for p in parents {
+                children.entry(*p).or_default().push(*id);
+            }
+        }
 
-            for parent in header.parents_hash() {
-		if !self.header_map.contains_key(&parent) {
+        let mut ready: BinaryHeap<_> = pending
+            .iter()
+            .filter(|(_, n)| **n == 0)
+            .map(|(id, _)| key(id))
+            .collect();
+        let mut order = Vec::with_capacity(self.header_map.len());
+        while let Some(Reverse((_, _, id))) = ready.pop() {
+            order.push(id);
+            for child in children.get(&id).into_iter().flatten() {
+                let n = pending.get_mut(child).expect("child is in the view");
+                *n -= 1;
+                if *n == 0 {
+                    ready.push(key(child));
+                }
+            }
+        }
+        order
+    }
+
+    // Edges to parents inside the view, or with `stub` set, to parents outside it.
+    // Grouped by child in canonical order, then by parent hash.
+    fn parent_edges(&self, stub: bool) -> Vec<DagEdge> {
+        let mut edges = Vec::new();
+
+        for &id in self.canonical_order() {
+            let header = &self.header_map[&id];
+            let selected_parent = self.ghostdag_map.get(&id).map(|gd| gd.selected_parent);
+            let mut parents = header.parents_hash();
+            parents.sort();
+
+            for parent in parents {
+                if self.header_map.contains_key(&parent) == stub {
                     continue;
-		}
+                }
                 edges.push(DagEdge {
                     from: parent,
-                    to: *id,
+                    to: id,
                     is_selected: Some(parent) == selected_parent,
+                    is_stub: stub,
                 });
             }
         }
@@ -111,6 +338,7 @@ where
     async fn dag_view(
         &self,
         window: BlockWindow,
+        limits: CrawlLimits,
     ) -> anyhow::Result<Box<dyn DagGraphProvider + Send + Sync>> {
         // Only ghostdag data for blocks on the selected chain are required.
         //
@@ -127,12 +355,39 @@ where
         //   1. The headers of all selected + mergeset blocks
         //   2. The ghostdag data of the selected chain blocks only
 
-        let selected_chain = {
+        // Every chain block is a node, so at most `max_nodes` of the latest are read.
+        let max_chain = limits.max_nodes as u64;
+        let chain_truncated = match window {
+            BlockWindow::Latest(n) | BlockWindow::Between { count: n, .. } => n > max_chain,
+        };
+        // A view ending at the selected tip also reads the node's tips, which the
+        // virtual block is mined on.
+        let (selected_chain, node_tips) = {
             match window {
-                BlockWindow::Latest(n) => self.get_selected_chain(None, n).await?,
-                _ => unimplemented!(),
+                BlockWindow::Latest(n) if limits.side_ghostdag => {
+                    let (chain, tips) = futures::try_join!(
+                        self.get_selected_chain(None, n.min(max_chain)),
+                        self.get_dag_tips()
+                    )?;
+                    (chain, Some(tips))
+                }
+                BlockWindow::Latest(n) => {
+                    let chain = self.get_selected_chain(None, n.min(max_chain)).await?;
+                    (chain, None)
+                }
+                BlockWindow::Between { from, count } => {
+                    let last = from.saturating_add(count.saturating_sub(1));
+                    let chain = self
+                        .get_selected_chain(Some(last), count.min(max_chain))
+                        .await?;
+                    (chain, None)
+                }
             }
         };
+        // An empty chain yields an empty view rather than a panic below.
+        let Some(min_selected_number) = selected_chain.iter().map(|h| h.number).min() else {
+            return Ok(Box::<DagBuildContext>::default());
+        };
         let mut header_map: HashMap<_, _> =
             selected_chain.iter().map(|h| (h.id(), h.clone())).collect();
         let selected_ids: Vec<_> = header_map.keys().copied().collect();
@@ -142,49 +397,434 @@ where
         let mut seen: HashSet<_> = header_map.keys().copied().collect();
         let mut to_fetch = VecDeque::new();
 
-        for (i, opt) in ghostdag_vec.into_iter().enumerate() {
+        for (&id, opt) in selected_ids.iter().zip(ghostdag_vec) {
             if let Some(gd) = opt {
-                let id = selected_ids[i];
-                ghostdag_map.insert(id, gd.clone());
                 for &h in gd.mergeset_blues.iter().chain(&gd.mergeset_reds) {
                     if seen.insert(h) {
                         to_fetch.push_back(h);
                     }
                 }
+                ghostdag_map.insert(id, gd);
             }
         }
-        // Collect all needed block hashes
-        let mut missing = Vec::new();
+
+        // Every hash queued for fetching counts against the node budget, so
+        // `header_map.len() + pending.len()` never exceeds `limits.max_nodes`.
+        let mut truncated = chain_truncated;
+        let mut pending: HashSet<HashValue> = HashSet::new();
         while let Some(hash) = to_fetch.pop_front() {
-            if !header_map.contains_key(&hash) {
-                missing.push(hash);
+            if header_map.len() + pending.len() >= limits.max_nodes {
+                truncated = true;
+                break;
             }
+            pending.insert(hash);
         }
 
-        // Fetch all missing headers in batch
-	let min_selected_number = selected_chain.last().unwrap().number;
-
-        let mut pending: HashSet<HashValue> = missing.iter().copied().collect();
+        // Fetch all missing headers in batch. Hashes the reader does not
+        // return are reported as missing instead of being requested again.
+        let mut missing = Vec::new();
         while !pending.is_empty() {
-            let batch: Vec<HashValue> = pending.iter().copied().collect();
+            let batch: Vec<HashValue> = pending.drain().collect();
             let new_headers = self.get_headers(&batch).await?;
+            let mut requested: HashSet<HashValue> = batch.into_iter().collect();
             for header in new_headers {
                 let id = header.id();
-                pending.remove(&id);
-		if header.number>=min_selected_number{
-                for p in header.parents_hash() {
+                if !requested.remove(&id) {
+                    continue;
+                }
+                let parents = if header.number >= min_selected_number {
+                    header.parents_hash()
+                } else {
+                    Vec::new()
+                };
+                // Counted before its parents are queued so the budget check sees it.
+                header_map.insert(id, header);
+                for p in parents {
+                    if header_map.len() + pending.len() + requested.len() >= limits.max_nodes {
+                        truncated = true;
+                        break;
+                    }
                     if seen.insert(p) {
                         pending.insert(p);
                     }
                 }
-		}
-                header_map.insert(id, header);
             }
+            missing.extend(requested);
         }
 
+        // Blue scores of the remaining blocks, and the blue work of the tips and
+        // mergeset of the virtual block, come from their own ghostdag data.
+        let side_ids: Vec<_> = header_map
+            .keys()
+            .chain(node_tips.iter().flatten())
+            .filter(|id| !ghostdag_map.contains_key(id))
+            .copied()
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        let side_ghostdag = if side_ids.is_empty() || !limits.side_ghostdag {
+            HashMap::new()
+        } else {
+            side_ids
+                .iter()
+                .zip(self.get_ghostdag_data(&side_ids).await?)
+                .filter_map(|(id, opt)| opt.map(|gd| (*id, gd)))
+                .collect()
+        };
+
         Ok(Box::new(DagBuildContext {
             header_map,
             ghostdag_map,
+            missing,
+            truncated,
+            side_ghostdag,
+            node_tips,
+            order: OnceLock::new(),
+        }))
+    }
+
+    async fn block_detail(&self, id: HashValue) -> anyhow::Result<Option<BlockDetail>> {
+        let ids = [id];
+        let (header, ghostdag) =
+            futures::try_join!(self.get_header_with_raw(id), self.get_ghostdag_data(&ids))?;
+        let Some((header, raw)) = header else {
+            return Ok(None);
+        };
+        let chain_block = self.get_selected_chain(Some(header.number), 1).await?;
+        Ok(Some(BlockDetail {
+            id,
+            number: header.number,
+            timestamp: header.timestamp,
+            parents: header.parents_hash,
+            header: raw,
+            ghostdag: ghostdag.into_iter().next().flatten(),
+            is_selected_chain: chain_block.iter().any(|h| h.id == id),
         }))
     }
+
+    async fn block_bodies(&self, ids: &[HashValue]) -> anyhow::Result<Vec<Option<BlockBody>>> {
+        self.get_block_bodies(ids).await
+    }
+
+    async fn selected_chain(&self, window: BlockWindow) -> anyhow::Result<Vec<ChainBlock>> {
+        let (number, count) = match window {
+            BlockWindow::Latest(n) => (None, n),
+            BlockWindow::Between { from, count } => {
+                (Some(from.saturating_add(count.saturating_sub(1))), count)
+            }
+        };
+        if count == 0 {
+            return Ok(Vec::new());
+        }
+        // One more block than asked for, to get the first block's time delta.
+        let mut chain = self.get_selected_chain(number, count + 1).await?;
+        chain.sort_by_key(|h| h.number);
+        let first = match window {
+            BlockWindow::Latest(_) => chain.len().saturating_sub(count as usize),
+            BlockWindow::Between { from, .. } => chain.partition_point(|h| h.number < from),
+        };
+        let ids: Vec<_> = chain.iter().map(|h| h.id).collect();
+        let ghostdag = self.get_ghostdag_data(&ids).await?;
+
+        Ok(chain
+            .iter()
+            .zip(ghostdag)
+            .enumerate()
+            .skip(first)
+            .map(|(i, (header, gd))| ChainBlock {
+                id: header.id,
+                number: header.number,
+                timestamp: header.timestamp,
+                blue_score: gd.as_ref().map(|gd| gd.blue_score),
+                mergeset_blues: gd.as_ref().map_or(0, |gd| gd.mergeset_blues.len()),
+                mergeset_reds: gd.as_ref().map_or(0, |gd| gd.mergeset_reds.len()),
+                time_delta: i
+                    .checked_sub(1)
+                    .map(|prev| header.timestamp.saturating_sub(chain[prev].timestamp)),
+            })
+            .collect())
+    }
+
+    async fn selected_tip(&self) -> anyhow::Result<Option<BlockHeader>> {
+        Ok(self.get_selected_chain(None, 1).await?.pop())
+    }
+
+    async fn dag_tips(&self) -> anyhow::Result<Vec<BlockHeader>> {
+        let ids = self.get_dag_tips().await?;
+        let mut tips = self.get_headers(&ids).await?;
+        tips.sort_by_key(|h| (Reverse(h.number), h.id));
+        Ok(tips)
+    }
+
+    async fn search(&self, query: &BlockQuery) -> anyhow::Result<Vec<BlockHeader>> {
+        let mut found = Vec::new();
+        if let Some(number) = query.number {
+            let chain = self.get_selected_chain(Some(number), 1).await?;
+            found.extend(chain.into_iter().filter(|h| h.number == number));
+            found.extend(self.find_by_number(number).await?);
+        }
+        if let Some(prefix) = &query.prefix {
+            match HashValue::from_hex(prefix) {
+                Ok(id) => found.extend(self.get_headers(&[id]).await?),
+                Err(_) => found.extend(self.find_by_prefix(prefix).await?),
+            }
+        }
+        found.sort_by_key(|h| (Reverse(h.number), h.id));
+        found.dedup_by_key(|h| h.id);
+        Ok(found)
+    }
+}
+
+#[tokio::test]
+async fn test_dag_nodes_merged_by() -> anyhow::Result<()> {
+    let reader = super::mock::MockChainReader::new();
+    let view = super::mock::mock_view(&reader).await?;
+    let nodes = view.dag_nodes();
+    let red = nodes
+        .iter()
+        .find(|n| matches!(n.color, NodeColor::Red))
+        .expect("mock view has one red block");
+    assert_eq!(red.mergeset_index, Some(2));
+    let merger = nodes
+        .iter()
+        .find(|n| Some(n.id) == red.merged_by)
+        .expect("merging block is in the view");
+    assert_eq!(merger.score, 7);
+    Ok(())
+}
+
+#[test]
+fn test_boundary_nodes_and_stub_edges() {
+    let outside = HashValue::random();
+    let root = BlockHeader {
+        id: HashValue::random(),
+        number: 10,
+        timestamp: 0,
+        parents_hash: vec![outside],
+    };
+    let child = BlockHeader {
+        id: HashValue::random(),
+        number: 11,
+        timestamp: 0,
+        parents_hash: vec![root.id],
+    };
+    let ctx = DagBuildContext {
+        header_map: [(root.id, root.clone()), (child.id, child.clone())].into(),
+        ghostdag_map: HashMap::new(),
+        ..Default::default()
+    };
+
+    let nodes = ctx.dag_nodes();
+    let root_node = nodes.iter().find(|n| n.id == root.id).unwrap();
+    assert!(root_node.is_boundary);
+    assert_eq!(root_node.omitted_parents, 1);
+    assert!(!nodes.iter().find(|n| n.id == child.id).unwrap().is_boundary);
+
+    assert_eq!(ctx.dag_edges().len(), 1);
+    let stubs = ctx.dag_stub_edges();
+    assert_eq!(stubs.len(), 1);
+    assert!(stubs[0].is_stub);
+    assert_eq!((stubs[0].from, stubs[0].to), (outside, root.id));
+}
+
+// Serves the mock's selected chain but never returns any other header.
+#[cfg(test)]
+struct HeaderlessReader(super::mock::MockChainReader);
+
+#[cfg(test)]
+#[async_trait]
+impl ChainReaderExt for HeaderlessReader {
+    async fn get_selected_chain(
+        &self,
+        number: Option<u64>,
+        count: u64,
+    ) -> anyhow::Result<Vec<BlockHeader>> {
+        self.0.get_selected_chain(number, count).await
+    }
+
+    async fn get_ghostdag_data(
+        &self,
+        ids: &[HashValue],
+    ) -> anyhow::Result<Vec<Option<GhostdagData>>> {
+        self.0.get_ghostdag_data(ids).await
+    }
+
+    async fn get_headers(&self, _ids: &[HashValue]) -> anyhow::Result<Vec<BlockHeader>> {
+        Ok(vec![])
+    }
+}
+
+#[tokio::test]
+async fn test_block_detail() -> anyhow::Result<()> {
+    let reader = super::mock::MockChainReader::new();
+    let view = super::mock::mock_view(&reader).await?;
+    let nodes = view.dag_nodes();
+    let red = nodes.iter().find(|n| n.color == NodeColor::Red).unwrap();
+    let merger = reader.block_detail(red.merged_by.unwrap()).await?.unwrap();
+    assert!(merger.is_selected_chain);
+    assert_eq!(merger.ghostdag.unwrap().mergeset_reds, vec![red.id]);
+
+    let detail = reader.block_detail(red.id).await?.unwrap();
+    assert!(!detail.is_selected_chain);
+    assert_eq!(detail.number, red.score);
+    assert!(reader.block_detail(HashValue::random()).await?.is_none());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_selected_chain_summary() -> anyhow::Result<()> {
+    let reader = super::mock::MockChainReader::new();
+    let chain = reader.selected_chain(BlockWindow::Latest(4)).await?;
+    assert_eq!(chain.len(), 4);
+    assert_eq!(chain[0].time_delta, None);
+    let tip = &chain[3];
+    assert_eq!((tip.mergeset_blues, tip.mergeset_reds), (2, 1));
+    assert_eq!(tip.time_delta, Some(9_000));
+
+    let latest = reader.selected_chain(BlockWindow::Latest(2)).await?;
+    assert_eq!(latest.iter().map(|b| b.number).collect::<Vec<_>>(), vec![4, 7]);
+    assert_eq!(latest[0].time_delta, Some(6_000));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_dag_view_reports_missing_headers() -> anyhow::Result<()> {
+    let reader = HeaderlessReader(super::mock::MockChainReader::new());
+    let view = super::mock::mock_view(&reader).await?;
+    assert_eq!(view.dag_nodes().len(), 4);
+    assert_eq!(view.missing_blocks().len(), 4);
+    assert!(!view.is_truncated());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_dag_view_respects_node_budget() -> anyhow::Result<()> {
+    let reader = super::mock::MockChainReader::new();
+    let view = reader
+        .dag_view(BlockWindow::Latest(4), CrawlLimits { max_nodes: 6, ..Default::default() })
+        .await?;
+    assert_eq!(view.dag_nodes().len(), 6);
+    assert!(view.is_truncated());
+    Ok(())
+}
+
+// A two block chain whose tip merges a side block with many parents of its own,
+// so the crawl queues parents while handling a fetched header.
+#[cfg(test)]
+struct WideParentsReader {
+    chain: Vec<BlockHeader>,
+    headers: HashMap<HashValue, BlockHeader>,
+    ghostdag: HashMap<HashValue, GhostdagData>,
+}
+
+#[cfg(test)]
+impl WideParentsReader {
+    fn new(width: usize) -> Self {
+        let header = |number, parents_hash| BlockHeader {
+            id: HashValue::random(),
+            number,
+            timestamp: 0,
+            parents_hash,
+        };
+        let parents: Vec<_> = (0..width).map(|_| header(2, vec![])).collect();
+        let side = header(2, parents.iter().map(|h| h.id).collect());
+        let root = header(1, vec![]);
+        let tip = header(3, vec![root.id, side.id]);
+        let ghostdag = GhostdagData {
+            blue_score: 2,
+            blue_work: 2,
+            selected_parent: root.id,
+            mergeset_blues: vec![root.id, side.id],
+            mergeset_reds: vec![],
+            blues_anticone_sizes: BTreeMap::new(),
+        };
+        let chain = vec![root.clone(), tip.clone()];
+        Self {
+            ghostdag: [(tip.id, ghostdag)].into(),
+            headers: parents
+                .into_iter()
+                .chain([side, root, tip])
+                .map(|h| (h.id, h))
+                .collect(),
+            chain,
+        }
+    }
+}
+
+#[cfg(test)]
+#[async_trait]
+impl ChainReaderExt for WideParentsReader {
+    async fn get_selected_chain(
+        &self,
+        _number: Option<u64>,
+        count: u64,
+    ) -> anyhow::Result<Vec<BlockHeader>> {
+        let skip = self.chain.len().saturating_sub(count as usize);
+        Ok(self.chain[skip..].to_vec())
+    }
+
+    async fn get_ghostdag_data(
+        &self,
+        ids: &[HashValue],
+    ) -> anyhow::Result<Vec<Option<GhostdagData>>> {
+        Ok(ids.iter().map(|id| self.ghostdag.get(id).cloned()).collect())
+    }
+
+    async fn get_headers(&self, ids: &[HashValue]) -> anyhow::Result<Vec<BlockHeader>> {
+        Ok(ids.iter().filter_map(|id| self.headers.get(id).cloned()).collect())
+    }
+}
+
+#[tokio::test]
+async fn test_node_budget_cut_while_queueing_parents() -> anyhow::Result<()> {
+    let reader = WideParentsReader::new(5);
+    // Below 2 the budget also cuts the selected chain itself.
+    for max_nodes in 1..8 {
+        let view = reader
+            .dag_view(BlockWindow::Latest(2), CrawlLimits { max_nodes, ..Default::default() })
+            .await?;
+        assert!(view.dag_nodes().len() <= max_nodes);
+        assert!(view.is_truncated());
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_dag_view_order_is_canonical() -> anyhow::Result<()> {
+    let reader = super::mock::MockChainReader::new();
+    let first = super::mock::mock_view(&reader).await?;
+    let second = super::mock::mock_view(&reader).await?;
+
+    let ids: Vec<_> = first.dag_nodes().iter().map(|n| n.id).collect();
+    assert_eq!(ids, second.dag_nodes().iter().map(|n| n.id).collect::<Vec<_>>());
+    let edges: Vec<_> = first.dag_edges().iter().map(|e| (e.from, e.to)).collect();
+    assert_eq!(
+        edges,
+        second
+            .dag_edges()
+            .iter()
+            .map(|e| (e.from, e.to))
+            .collect::<Vec<_>>()
+    );
+
+    let position: HashMap<_, _> = ids.iter().enumerate().map(|(i, id)| (*id, i)).collect();
+    for (from, to) in edges {
+        assert!(position[&from] < position[&to]);
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_view_without_side_ghostdag() -> anyhow::Result<()> {
+    let reader = super::mock::MockChainReader::new();
+    let limits = CrawlLimits::default().without_side_ghostdag();
+    let view = reader.dag_view(BlockWindow::Latest(4), limits).await?;
+    let full = super::mock::mock_view(&reader).await?;
+
+    // Only the selected chain blocks keep their blue scores.
+    let scored = |nodes: Vec<DagNode>| nodes.iter().filter(|n| n.blue_score.is_some()).count();
+    assert_eq!(scored(view.dag_nodes()), view.chain_blocks().len());
+    assert!(scored(full.dag_nodes()) > scored(view.dag_nodes()));
+    assert_eq!(view.dag_stats().tip_blue_score, full.dag_stats().tip_blue_score);
+    Ok(())
 }
@@ -1,13 +1,23 @@
-use crate::chain_reader::ext::{BlockHeader, GhostdagData};
+use crate::chain_reader::ext::{BlockBody, BlockHeader, GhostdagData};
 use async_trait::async_trait;
 use starcoin_crypto::HashValue;
 use std::collections::HashMap;
 
 use super::ext::ChainReaderExt;
+#[cfg(test)]
+use super::{BlockWindow, ChainReader, CrawlLimits};
+#[cfg(test)]
+use crate::dag_graph::{DagGraphBuilder, DagGraphProvider};
+#[cfg(test)]
+use std::sync::Arc;
+
 pub struct MockChainReader {
     selected_chain: Vec<BlockHeader>,
     ghostdag_map: HashMap<HashValue, GhostdagData>,
     header_map: HashMap<HashValue, BlockHeader>,
+    bodies: HashMap<HashValue, BlockBody>,
+    shared_transaction: HashValue,
+    tips: Vec<HashValue>,
 }
 
 impl MockChainReader {
@@ -16,6 +26,7 @@ impl MockChainReader {
         let block_a = BlockHeader {
             id: HashValue::random(),
             number: 0,
+            timestamp: 1_700_000_000_000,
             parents_hash: vec![],
         };
 
@@ -23,6 +34,7 @@ impl MockChainReader {
         let block_b = BlockHeader {
             id: HashValue::random(),
             number: 1,
+            timestamp: 1_700_000_003_000,
             parents_hash: vec![block_a.id],
         };
 
@@ -30,6 +42,7 @@ impl MockChainReader {
         let block_c = BlockHeader {
             id: HashValue::random(),
             number: 2,
+            timestamp: 1_700_000_006_000,
             parents_hash: vec![block_b.id],
         };
 
@@ -37,6 +50,7 @@ impl MockChainReader {
         let block_d = BlockHeader {
             id: HashValue::random(),
             number: 3,
+            timestamp: 1_700_000_009_000,
             parents_hash: vec![block_b.id],
         };
 
@@ -44,6 +58,7 @@ impl MockChainReader {
         let block_e = BlockHeader {
             id: HashValue::random(),
             number: 4,
+            timestamp: 1_700_000_012_000,
             parents_hash: vec![block_c.id, block_d.id],
         };
 
@@ -51,6 +66,7 @@ impl MockChainReader {
         let block_f = BlockHeader {
             id: HashValue::random(),
             number: 5,
+            timestamp: 1_700_000_015_000,
             parents_hash: vec![block_d.id],
         };
 
@@ -58,6 +74,7 @@ impl MockChainReader {
         let block_g = BlockHeader {
             id: HashValue::random(),
             number: 6,
+            timestamp: 1_700_000_018_000,
             parents_hash: vec![block_d.id],
         };
 
@@ -65,51 +82,74 @@ impl MockChainReader {
         let block_h = BlockHeader {
             id: HashValue::random(),
             number: 7,
+            timestamp: 1_700_000_021_000,
             parents_hash: vec![block_e.id, block_f.id, block_g.id],
         };
 
+        // block I, a sibling of H that no block merges yet
+        let block_i = BlockHeader {
+            id: HashValue::random(),
+            number: 7,
+            timestamp: 1_700_000_022_000,
+            parents_hash: vec![block_g.id],
+        };
+
         let mut ghostdag_map = HashMap::new();
         let ghostdag_b = GhostdagData {
             blue_score: 1,
+            blue_work: 1,
             selected_parent: block_a.id(),
             mergeset_blues: vec![block_a.id()],
             mergeset_reds: vec![],
+            blues_anticone_sizes: [(block_a.id(), 0)].into(),
         };
         let ghostdag_c = GhostdagData {
             blue_score: 2,
+            blue_work: 2,
             selected_parent: block_b.id(),
             mergeset_blues: vec![block_b.id()],
             mergeset_reds: vec![],
+            blues_anticone_sizes: [(block_b.id(), 0)].into(),
         };
         let ghostdag_d = GhostdagData {
             blue_score: 3,
+            blue_work: 3,
             selected_parent: block_b.id(),
             mergeset_blues: vec![block_b.id()],
             mergeset_reds: vec![],
+            blues_anticone_sizes: [(block_b.id(), 0)].into(),
         };
         let ghostdag_e = GhostdagData {
             blue_score: 4,
+            blue_work: 4,
             selected_parent: block_c.id(),
             mergeset_blues: vec![block_c.id(), block_d.id()],
             mergeset_reds: vec![],
+            blues_anticone_sizes: [(block_c.id(), 1), (block_d.id(), 1)].into(),
         };
         let ghostdag_f = GhostdagData {
             blue_score: 5,
+            blue_work: 5,
             selected_parent: block_d.id(),
             mergeset_blues: vec![block_d.id()],
             mergeset_reds: vec![],
+            blues_anticone_sizes: [(block_d.id(), 0)].into(),
         };
         let ghostdag_g = GhostdagData {
             blue_score: 5,
+            blue_work: 5,
             selected_parent: block_d.id(),
             mergeset_blues: vec![block_d.id()],
             mergeset_reds: vec![],
+            blues_anticone_sizes: [(block_d.id(), 0)].into(),
         };
         let ghostdag_h = GhostdagData {
             blue_score: 6,
+            blue_work: 6,
             selected_parent: block_e.id(),
             mergeset_blues: vec![block_e.id(), block_f.id()],
             mergeset_reds: vec![block_g.id()],
+            blues_anticone_sizes: [(block_e.id(), 1), (block_f.id(), 2)].into(),
         };
 
         ghostdag_map.insert(block_b.id(), ghostdag_b);
@@ -129,24 +169,116 @@ impl MockChainReader {
         header_map.insert(block_f.id(), block_f.clone());
         header_map.insert(block_g.id(), block_g.clone());
         header_map.insert(block_h.id(), block_h.clone());
+        header_map.insert(block_i.id(), block_i.clone());
+        // Every block but genesis carries a transaction of its own; the parallel
+        // blocks F (blue) and G (red) both include a shared one as well.
+        let shared_transaction = HashValue::random();
+        let bodies = header_map
+            .values()
+            .filter(|h| h.number > 0)
+            .map(|h| {
+                let mut transactions = vec![HashValue::random()];
+                if h.id == block_f.id || h.id == block_g.id {
+                    transactions.push(shared_transaction);
+                }
+                let gas_used = 1_000 * transactions.len() as u64;
+                (h.id, BlockBody { transactions, gas_used })
+            })
+            .collect();
+        let tips = vec![block_h.id, block_i.id];
         let selected_chain = vec![block_b, block_c, block_e, block_h];
 
         Self {
             selected_chain,
             ghostdag_map,
             header_map,
+            bodies,
+            shared_transaction,
+            tips,
         }
     }
+
+    /// A chain A, B, C, D, E where E also merges S, a block on A as old as B, so
+    /// E's mergeset reaches back past C's and D's.
+    pub fn with_late_merge() -> Self {
+        let header = |number: u64, parents_hash: Vec<HashValue>| BlockHeader {
+            id: HashValue::random(),
+            number,
+            timestamp: 1_700_000_000_000 + number * 3_000,
+            parents_hash,
+        };
+        let mut selected_chain = vec![header(0, vec![])];
+        for number in 1..5 {
+            let parent = selected_chain[number as usize - 1].id;
+            selected_chain.push(header(number, vec![parent]));
+        }
+        let side = header(1, vec![selected_chain[0].id]);
+        selected_chain[4].parents_hash.push(side.id);
+
+        let mut ghostdag_map = HashMap::new();
+        for (i, block) in selected_chain.iter().enumerate().skip(1) {
+            let parent = selected_chain[i - 1].id;
+            let mut mergeset_blues = vec![parent];
+            if i == 4 {
+                mergeset_blues.push(side.id);
+            }
+            // Genesis is blue, and every block counts its mergeset's blues.
+            let blue_score = i as u64 + mergeset_blues.len() as u64 - 1;
+            let gd = GhostdagData {
+                blue_score,
+                blue_work: blue_score as u128,
+                selected_parent: parent,
+                blues_anticone_sizes: mergeset_blues.iter().map(|b| (*b, 0)).collect(),
+                mergeset_blues,
+                mergeset_reds: vec![],
+            };
+            ghostdag_map.insert(block.id, gd);
+        }
+        let header_map = selected_chain
+            .iter()
+            .chain([&side])
+            .map(|h| (h.id, h.clone()))
+            .collect();
+        let tips = vec![selected_chain[4].id];
+
+        Self {
+            selected_chain,
+            ghostdag_map,
+            header_map,
+            bodies: HashMap::new(),
+            shared_transaction: HashValue::random(),
+            tips,
+        }
+    }
+
+    /// The transaction included by both parallel blocks F and G.
+    pub fn shared_transaction(&self) -> HashValue {
+        self.shared_transaction
+    }
+}
+
+impl Default for MockChainReader {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[async_trait]
 impl ChainReaderExt for MockChainReader {
     async fn get_selected_chain(
         &self,
-        _number: Option<u64>,
-        _count: u64,
+        number: Option<u64>,
+        count: u64,
     ) -> anyhow::Result<Vec<BlockHeader>> {
-        Ok(self.selected_chain.clone())
+        // The last `count` chain blocks up to `number`; the mock chain skips numbers.
+        let mut chain: Vec<_> = self
+            .selected_chain
+            .iter()
+            .filter(|h| number.is_none_or(|n| h.number <= n))
+            .cloned()
+            .collect();
+        let skip = chain.len().saturating_sub(count as usize);
+        Ok(chain.split_off(skip))
     }
 
     async fn get_ghostdag_data(
@@ -167,4 +299,30 @@ impl ChainReaderExt for MockChainReader {
             .collect();
         Ok(ret)
     }
+
+    async fn get_dag_tips(&self) -> anyhow::Result<Vec<HashValue>> {
+        Ok(self.tips.clone())
+    }
+
+    async fn get_block_bodies(&self, ids: &[HashValue]) -> anyhow::Result<Vec<Option<BlockBody>>> {
+        Ok(ids.iter().map(|id| self.bodies.get(id).cloned()).collect())
+    }
+}
+
+#[cfg(test)]
+impl MockChainReader {
+    /// Graph builder over the latest four blocks of a fresh mock chain.
+    pub fn builder() -> DagGraphBuilder {
+        DagGraphBuilder::new(Arc::new(Self::new()), BlockWindow::Latest(4))
+    }
+}
+
+/// View of the latest four blocks with default limits.
+#[cfg(test)]
+pub async fn mock_view(
+    reader: &impl ChainReader,
+) -> anyhow::Result<Box<dyn DagGraphProvider + Send + Sync>> {
+    reader
+        .dag_view(BlockWindow::Latest(4), CrawlLimits::default())
+        .await
 }
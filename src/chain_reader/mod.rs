@@ -1,20 +1,113 @@
 use crate::dag_graph::DagGraphProvider;
 use async_trait::async_trait;
+use ext::{BlockBody, BlockDetail, BlockHeader, ChainBlock};
+use starcoin_crypto::HashValue;
+use std::str::FromStr;
 
 #[derive(Clone,Copy)]
 pub enum BlockWindow{
     Latest(u64),
+    /// `count` selected chain blocks starting at number `from`.
     Between{from:u64, count:u64}
 }
 
+/// Hex digits a hash prefix needs before it is looked up.
+pub const MIN_PREFIX_LEN: usize = 4;
+
+/// A block search: a number, a full hash, or a hash prefix. A string of
+/// decimal digits long enough to be a prefix is tried as both.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BlockQuery {
+    pub number: Option<u64>,
+    /// Lowercase hex without `0x`; 64 digits for a full hash.
+    pub prefix: Option<String>,
+}
+
+impl FromStr for BlockQuery {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let s = s.trim();
+        let (hex, explicit) = match s.strip_prefix("0x") {
+            Some(hex) => (hex, true),
+            None => (s, false),
+        };
+        let number = if explicit { None } else { s.parse().ok() };
+        let prefix = (hex.len() >= MIN_PREFIX_LEN
+            && hex.len() <= 64
+            && hex.bytes().all(|b| b.is_ascii_hexdigit()))
+        .then(|| hex.to_ascii_lowercase());
+        if number.is_none() && prefix.is_none() {
+            anyhow::bail!(
+                "expected a block number or at least {} hex digits of a hash, got {:?}",
+                MIN_PREFIX_LEN,
+                s
+            );
+        }
+        Ok(Self { number, prefix })
+    }
+}
+
+/// Bounds on how much of the DAG a single `dag_view` call may fetch.
+#[derive(Clone, Copy, Debug)]
+pub struct CrawlLimits {
+    pub max_nodes: usize,
+    /// Also fetch the ghostdag data of blocks off the selected chain, which
+    /// their blue scores and the virtual block need.
+    pub side_ghostdag: bool,
+}
+
+impl Default for CrawlLimits {
+    fn default() -> Self {
+        Self {
+            max_nodes: 10_000,
+            side_ghostdag: true,
+        }
+    }
+}
+
+impl CrawlLimits {
+    /// The same bounds without the ghostdag data of blocks off the selected chain,
+    /// for views that only need the DAG's shape and the chain's colors.
+    pub fn without_side_ghostdag(self) -> Self {
+        Self {
+            side_ghostdag: false,
+            ..self
+        }
+    }
+}
+
 #[async_trait]
 pub trait ChainReader:Send+Sync {
     async fn dag_view(
         &self,
-	window: BlockWindow
+	window: BlockWindow,
+	limits: CrawlLimits,
     ) -> anyhow::Result<Box<dyn DagGraphProvider + Send + Sync>>;
+
+    /// Header and ghostdag data of a single block, or `None` if the chain does not know it.
+    async fn block_detail(&self, id: HashValue) -> anyhow::Result<Option<BlockDetail>>;
+
+    /// Bodies of `ids`, in order, or `None` where the reader cannot fetch one.
+    async fn block_bodies(&self, ids: &[HashValue]) -> anyhow::Result<Vec<Option<BlockBody>>>;
+
+    /// The selected chain blocks of `window`, oldest first.
+    async fn selected_chain(&self, window: BlockWindow) -> anyhow::Result<Vec<ChainBlock>>;
+
+    /// Header of the selected tip, or `None` for an empty chain.
+    async fn selected_tip(&self) -> anyhow::Result<Option<BlockHeader>>;
+
+    /// Every block the node knows without children, newest first.
+    async fn dag_tips(&self) -> anyhow::Result<Vec<BlockHeader>>;
+
+    /// Blocks matching `query`, newest first. Prefixes only match blocks the
+    /// reader has seen; numbers also match the selected chain block.
+    async fn search(&self, query: &BlockQuery) -> anyhow::Result<Vec<BlockHeader>>;
 }
 
+pub mod cache;
 pub mod ext;
 pub mod mock;
 pub mod rpc;
+mod stats;
+mod virtual_block;
@@ -1,16 +1,25 @@
-use crate::chain_reader::ext::{BlockHeader, ChainReaderExt, GhostdagData};
+use crate::chain_reader::ext::{BlockBody, BlockHeader, ChainReaderExt, GhostdagData};
+use crate::metrics::Metrics;
 
 use anyhow::Result;
+use futures::StreamExt;
 use serde::{Deserialize, Serialize};
 use starcoin_crypto::HashValue;
 use std::collections::HashMap;
 use std::convert::TryFrom;
+use std::num::IntErrorKind;
 use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+// Block bodies are fetched one request per block, this many at a time.
+const BODY_REQUESTS_IN_FLIGHT: usize = 16;
 
 pub struct RpcChainReader {
     rpc_url: String,
     client: reqwest::Client,
     id_counter: AtomicU64,
+    metrics: Option<Arc<Metrics>>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -29,13 +38,28 @@ impl TryFrom<RpcGhostdagData> for GhostdagData {
     fn try_from(rpc: RpcGhostdagData) -> Result<Self> {
         Ok(Self {
             blue_score: rpc.blue_score,
+            blue_work: parse_blue_work(&rpc.blue_work)?,
             selected_parent: rpc.selected_parent,
             mergeset_blues: rpc.mergeset_blues,
             mergeset_reds: rpc.mergeset_reds,
+            blues_anticone_sizes: rpc.blues_anticone_sizes.into_iter().collect(),
         })
     }
 }
 
+// Blue work is a u256 on chain, serialized either as a 0x-prefixed hex or a
+// decimal string. Values past a u128 saturate rather than failing the view.
+fn parse_blue_work(s: &str) -> Result<u128> {
+    let parsed = match s.strip_prefix("0x") {
+        Some(hex) => u128::from_str_radix(hex, 16),
+        None => s.parse::<u128>(),
+    };
+    match parsed {
+        Err(e) if *e.kind() == IntErrorKind::PosOverflow => Ok(u128::MAX),
+        parsed => Ok(parsed?),
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RpcBlockHeader {
     pub block_hash: String,
@@ -66,28 +90,90 @@ impl TryFrom<RpcBlockHeader> for BlockHeader {
             id: HashValue::from_hex_literal(&rpc.block_hash)?,
             parents_hash,
             number: rpc.number.parse::<u64>()?,
+            timestamp: rpc.timestamp.parse::<u64>()?,
         })
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RpcTransaction {
+    pub transaction_hash: HashValue,
+}
+
+/// The part of `chain.get_dag_state` graphd reads.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RpcDagState {
+    pub tips: Vec<HashValue>,
+}
+
+/// Transactions of a block, as hashes only or as full transactions.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum RpcBlockBody {
+    Hashes(Vec<HashValue>),
+    Full(Vec<RpcTransaction>),
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RpcBlock {
     pub header: RpcBlockHeader,
 }
 
+/// A block together with its body, as returned by `chain.get_block_by_hash`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RpcFullBlock {
+    pub header: RpcBlockHeader,
+    pub body: RpcBlockBody,
+}
+
+impl TryFrom<RpcFullBlock> for BlockBody {
+    type Error = anyhow::Error;
+
+    fn try_from(rpc: RpcFullBlock) -> Result<Self> {
+        let transactions = match rpc.body {
+            RpcBlockBody::Hashes(hashes) => hashes,
+            RpcBlockBody::Full(txns) => txns.into_iter().map(|t| t.transaction_hash).collect(),
+        };
+        Ok(BlockBody {
+            transactions,
+            gas_used: rpc.header.gas_used.parse()?,
+        })
+    }
+}
+
 impl RpcChainReader {
     pub fn new(rpc_url: impl Into<String>) -> Self {
         Self {
             rpc_url: rpc_url.into(),
             client: reqwest::Client::new(),
             id_counter: AtomicU64::new(1),
+            metrics: None,
         }
     }
 
+    /// Record the latency and failures of every call.
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
     async fn rpc_call<T: serde::de::DeserializeOwned>(
         &self,
         method: &str,
         params: serde_json::Value,
+    ) -> anyhow::Result<T> {
+        let start = Instant::now();
+        let result = self.send_rpc(method, params).await;
+        if let Some(metrics) = &self.metrics {
+            metrics.observe_rpc(method, start.elapsed(), result.is_ok());
+        }
+        result
+    }
+
+    async fn send_rpc<T: serde::de::DeserializeOwned>(
+        &self,
+        method: &str,
+        params: serde_json::Value,
     ) -> anyhow::Result<T> {
         let id = self.id_counter.fetch_add(1, Ordering::Relaxed);
         let req = serde_json::json!({
@@ -155,6 +241,45 @@ impl ChainReaderExt for RpcChainReader {
             .collect::<Result<Vec<_>, _>>()?;
         Ok(headers)
     }
+
+    async fn get_block_bodies(&self, ids: &[HashValue]) -> anyhow::Result<Vec<Option<BlockBody>>> {
+        let bodies = futures::stream::iter(ids.to_vec())
+            .map(|id| async move {
+                let params = serde_json::json!([id.to_hex_literal()]);
+                let block = self
+                    .rpc_call::<Option<RpcFullBlock>>("chain.get_block_by_hash", params)
+                    .await
+                    .and_then(|block| block.map(BlockBody::try_from).transpose());
+                // A body that fails to load leaves its block without counts
+                // rather than failing the whole view.
+                block.unwrap_or_else(|e| {
+                    eprintln!("failed to fetch the body of {}: {:#}", id.to_hex_literal(), e);
+                    None
+                })
+            })
+            .buffered(BODY_REQUESTS_IN_FLIGHT)
+            .collect()
+            .await;
+        Ok(bodies)
+    }
+
+    async fn get_dag_tips(&self) -> anyhow::Result<Vec<HashValue>> {
+        let state: RpcDagState = self.rpc_call("chain.get_dag_state", serde_json::json!([])).await?;
+        Ok(state.tips)
+    }
+
+    async fn get_header_with_raw(
+        &self,
+        id: HashValue,
+    ) -> anyhow::Result<Option<(BlockHeader, Option<serde_json::Value>)>> {
+        let params = serde_json::json!([[id.to_hex_literal()]]);
+        let mut headers: Vec<serde_json::Value> = self.rpc_call("chain.get_headers", params).await?;
+        let Some(raw) = headers.pop() else {
+            return Ok(None);
+        };
+        let header = serde_json::from_value::<RpcBlockHeader>(raw.clone())?.try_into()?;
+        Ok(Some((header, Some(raw))))
+    }
 }
 
 #[tokio::test]
@@ -188,3 +313,51 @@ async fn test_get_selected_chain() -> Result<()> {
     println!("{:?}", result);
     Ok(())
 }
+
+#[test]
+fn test_parse_blue_work_saturates() {
+    assert_eq!(parse_blue_work("0x1f").unwrap(), 31);
+    assert_eq!(parse_blue_work("42").unwrap(), 42);
+    let u256_max = format!("0x{}", "f".repeat(64));
+    assert_eq!(parse_blue_work(&u256_max).unwrap(), u128::MAX);
+    assert_eq!(parse_blue_work(&"9".repeat(60)).unwrap(), u128::MAX);
+    assert!(parse_blue_work("0xzz").is_err());
+}
+
+#[test]
+fn test_full_block_body_deserializes() -> Result<()> {
+    // Shaped like `chain.get_block_by_hash` from a dev node, fields graphd ignores trimmed.
+    let header = serde_json::json!({
+        "block_hash": "0x2aec8e48fb6d8c52ce09833f63146a822ee01c0ed58d42794f2c02d49cbfa66e",
+        "body_hash": "0xc01e0329de6d899348a8ef4bd51db56175b3fa0988e57c3dcec8eaf13a164d97",
+        "chain_id": 254,
+        "difficulty": "0x0b",
+        "extra": "0x00000000",
+        "gas_used": "21000",
+        "nonce": 3180829547u64,
+        "number": "42",
+        "parent_hash": "0x9a4b1e5e3b0b6e0b6f2d5a9d4e1c8f7a6b5c4d3e2f1a0b9c8d7e6f5a4b3c2d1e",
+        "parents_hash": ["0x9a4b1e5e3b0b6e0b6f2d5a9d4e1c8f7a6b5c4d3e2f1a0b9c8d7e6f5a4b3c2d1e"],
+        "pruning_point": "0x0000000000000000000000000000000000000000000000000000000000000000",
+        "timestamp": "1700000012000",
+        "version": 1,
+    });
+    let txn = "0x6f3a2b1c0d9e8f7a6b5c4d3e2f1a0b9c8d7e6f5a4b3c2d1e0f9a8b7c6d5e4f3a";
+    let full = serde_json::json!({
+        "header": header,
+        "body": [{
+            "transaction_hash": txn,
+            "block_hash": "0x2aec8e48fb6d8c52ce09833f63146a822ee01c0ed58d42794f2c02d49cbfa66e",
+            "raw_txn": { "sender": "0x0000000000000000000000000a550c18", "sequence_number": "7" },
+        }],
+        "uncles": [],
+    });
+    let body = BlockBody::try_from(serde_json::from_value::<RpcFullBlock>(full)?)?;
+    assert_eq!(body.transactions, vec![HashValue::from_hex_literal(txn)?]);
+    assert_eq!(body.gas_used, 21_000);
+
+    let hashes = serde_json::json!({ "header": header, "body": [txn] });
+    let body = BlockBody::try_from(serde_json::from_value::<RpcFullBlock>(hashes)?)?;
+    assert_eq!(body.transactions, vec![HashValue::from_hex_literal(txn)?]);
+    Ok(())
+}
@@ -0,0 +1,85 @@
+use crate::chain_reader::ext::DagBuildContext;
+use crate::dag_graph::DagStats;
+use std::collections::{BTreeMap, HashMap};
+
+impl DagBuildContext {
+    /// Statistics over the fetched headers and the selected chain's ghostdag data.
+    /// Rates are `None` when the blocks involved span no time.
+    pub fn stats(&self) -> DagStats {
+        let timestamps = self.header_map.values().map(|h| h.timestamp);
+        let time_span = timestamps.clone().max().unwrap_or(0) - timestamps.min().unwrap_or(0);
+
+        let mut width: HashMap<u64, usize> = HashMap::new();
+        let mut parents_per_block = BTreeMap::new();
+        for header in self.header_map.values() {
+            *width.entry(header.number).or_default() += 1;
+            *parents_per_block.entry(header.parents_hash.len()).or_default() += 1;
+        }
+
+        let mut mergeset_sizes = BTreeMap::new();
+        let (mut blues, mut reds) = (0, 0);
+        for gd in self.ghostdag_map.values() {
+            blues += gd.mergeset_blues.len();
+            reds += gd.mergeset_reds.len();
+            *mergeset_sizes
+                .entry(gd.mergeset_blues.len() + gd.mergeset_reds.len())
+                .or_default() += 1;
+        }
+
+        let chain_times: Vec<_> = self
+            .ghostdag_map
+            .keys()
+            .filter_map(|id| self.header_map.get(id))
+            .map(|h| h.timestamp)
+            .collect();
+        let chain_span = chain_times.iter().max().unwrap_or(&0) - chain_times.iter().min().unwrap_or(&0);
+
+        let blocks = self.header_map.len();
+        DagStats {
+            blocks,
+            selected_chain_blocks: self.ghostdag_map.len(),
+            tips: self.tips().len(),
+            tip_number: self.header_map.values().map(|h| h.number).max(),
+            tip_blue_score: self
+                .header_map
+                .keys()
+                .filter_map(|id| self.ghostdag(id))
+                .map(|gd| gd.blue_score)
+                .max(),
+            time_span,
+            blocks_per_second: per_second(blocks, time_span),
+            chain_growth_rate: per_second(chain_times.len(), chain_span),
+            average_width: if width.is_empty() {
+                0.0
+            } else {
+                blocks as f64 / width.len() as f64
+            },
+            max_width: width.values().copied().max().unwrap_or(0),
+            parents_per_block,
+            mergeset_sizes,
+            red_ratio: (blues + reds > 0).then(|| reds as f64 / (blues + reds) as f64),
+        }
+    }
+}
+
+// Blocks after the first per second of `span_ms`.
+fn per_second(blocks: usize, span_ms: u64) -> Option<f64> {
+    (span_ms > 0).then(|| blocks.saturating_sub(1) as f64 * 1000.0 / span_ms as f64)
+}
+
+#[tokio::test]
+async fn test_stats() -> anyhow::Result<()> {
+    use crate::chain_reader::mock::{mock_view, MockChainReader};
+
+    let view = mock_view(&MockChainReader::new()).await?;
+    let stats = view.dag_stats();
+    assert_eq!(stats.blocks, 8);
+    assert_eq!(stats.selected_chain_blocks, 4);
+    assert_eq!((stats.tips, stats.tip_number, stats.tip_blue_score), (1, Some(7), Some(6)));
+    assert_eq!(stats.time_span, 21_000);
+    assert_eq!(stats.blocks_per_second, Some(7.0 / 21.0));
+    assert_eq!(stats.parents_per_block, [(0, 1), (1, 5), (2, 1), (3, 1)].into());
+    assert_eq!(stats.mergeset_sizes, [(1, 2), (2, 1), (3, 1)].into());
+    assert_eq!(stats.red_ratio, Some(1.0 / 7.0));
+    Ok(())
+}
@@ -0,0 +1,131 @@
+use crate::chain_reader::ext::DagBuildContext;
+use crate::dag_graph::{Reachability, VirtualBlock};
+use starcoin_crypto::HashValue;
+use std::collections::HashSet;
+
+impl DagBuildContext {
+    /// Blocks of the view that no other block in the view references as a parent,
+    /// ordered by number then hash.
+    pub fn tips(&self) -> Vec<HashValue> {
+        let referenced: HashSet<_> = self
+            .header_map
+            .values()
+            .flat_map(|h| h.parents_hash.iter().copied())
+            .collect();
+        let mut tips: Vec<_> = self
+            .header_map
+            .values()
+            .filter(|h| !referenced.contains(&h.id))
+            .map(|h| (h.number, h.id))
+            .collect();
+        tips.sort();
+        tips.into_iter().map(|(_, id)| id).collect()
+    }
+
+    /// The virtual block over the node's tips, or over the view's tips for a view
+    /// that ends before the selected tip.
+    ///
+    /// The selected parent is the tip with the highest blue work (ties broken by hash),
+    /// following GHOSTDAG. Its mergeset is everything in the past of the other tips
+    /// that is not in the past of the selected parent, restricted to the view, and
+    /// ordered by blue work. Returns `None` when no tip has ghostdag data.
+    pub fn virtual_block(&self) -> Option<VirtualBlock> {
+        let parents = match &self.node_tips {
+            Some(tips) => {
+                let mut tips = tips.clone();
+                tips.sort();
+                tips
+            }
+            None => self.tips(),
+        };
+        let selected_parent = parents
+            .iter()
+            .filter_map(|id| Some((self.ghostdag(id)?.blue_work, *id)))
+            .max()?
+            .1;
+
+        let reach = Reachability::from(self);
+        let selected_past = reach.past(&selected_parent);
+        let mut merged: HashSet<_> = HashSet::new();
+        for tip in parents.iter().filter(|id| **id != selected_parent) {
+            merged.insert(*tip);
+            merged.extend(reach.past(tip));
+        }
+        // Blue work grows along every parent edge, so this order is topological.
+        let mut merged: Vec<_> = merged
+            .into_iter()
+            .filter(|id| *id != selected_parent && !selected_past.contains(id))
+            .map(|id| (self.ghostdag(&id).map_or(0, |gd| gd.blue_work), id))
+            .collect();
+        merged.sort();
+
+        let mut mergeset = vec![selected_parent];
+        mergeset.extend(merged.into_iter().map(|(_, id)| id));
+        Some(VirtualBlock {
+            parents,
+            selected_parent,
+            mergeset,
+        })
+    }
+}
+
+#[test]
+fn test_tips_and_virtual_block() {
+    use crate::chain_reader::ext::{BlockHeader, GhostdagData};
+
+    let header = |number, parents_hash| BlockHeader {
+        id: HashValue::random(),
+        number,
+        timestamp: 0,
+        parents_hash,
+    };
+    let ghostdag = |blue_work, selected_parent| GhostdagData {
+        blue_score: blue_work as u64,
+        blue_work,
+        selected_parent,
+        mergeset_blues: vec![selected_parent],
+        mergeset_reds: vec![],
+        blues_anticone_sizes: [(selected_parent, 0)].into(),
+    };
+    let root = header(0, vec![]);
+    let a = header(1, vec![root.id]);
+    let b = header(1, vec![root.id]);
+    let c = header(2, vec![a.id]);
+
+    let mut ctx = DagBuildContext {
+        header_map: [&root, &a, &b, &c]
+            .into_iter()
+            .map(|h| (h.id, h.clone()))
+            .collect(),
+        ..Default::default()
+    };
+    ctx.side_ghostdag.insert(b.id, ghostdag(2, root.id));
+    ctx.side_ghostdag.insert(c.id, ghostdag(3, a.id));
+
+    assert_eq!(ctx.tips(), vec![b.id, c.id]);
+    let virtual_block = ctx.virtual_block().expect("tips have ghostdag data");
+    assert_eq!(virtual_block.parents, vec![b.id, c.id]);
+    assert_eq!(virtual_block.selected_parent, c.id);
+    assert_eq!(virtual_block.mergeset, vec![c.id, b.id]);
+}
+
+#[tokio::test]
+async fn test_virtual_block_over_node_tips() -> anyhow::Result<()> {
+    use crate::chain_reader::mock::{mock_view, MockChainReader};
+    use crate::chain_reader::{BlockWindow, ChainReader, CrawlLimits};
+
+    // I is a tip of the node that no block of the view references.
+    let reader = MockChainReader::new();
+    let view = mock_view(&reader).await?;
+    let tip = view.dag_tips()[0];
+    let virtual_block = view.virtual_block().expect("tips have ghostdag data");
+    assert_eq!(virtual_block.parents.len(), 2);
+    assert_eq!(virtual_block.selected_parent, tip);
+    let other = virtual_block.parents.iter().find(|id| **id != tip).copied();
+    assert_eq!(virtual_block.mergeset, vec![tip, other.unwrap()]);
+
+    let window = BlockWindow::Between { from: 1, count: 4 };
+    let as_of = reader.dag_view(window, CrawlLimits::default()).await?;
+    assert_eq!(as_of.virtual_block().unwrap().parents, as_of.dag_tips());
+    Ok(())
+}
@@ -0,0 +1,120 @@
+use crate::dag_graph::{DagEdge, DagGraph, DagNode, NodeColor};
+use serde::{Deserialize, Serialize};
+use starcoin_crypto::HashValue;
+use std::collections::HashMap;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub struct ColorChange {
+    pub id: HashValue,
+    pub from: NodeColor,
+    pub to: NodeColor,
+}
+
+/// Changes that turn one `DagGraph` into another.
+///
+/// Edges are identified by their endpoints; an edge present in both graphs whose
+/// selected-parent flag flipped is listed in `reselected_edges` with its new value.
+/// Lists follow the node and edge order of the graph they come from.
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct GraphDiff {
+    pub added_nodes: Vec<DagNode>,
+    pub removed_nodes: Vec<HashValue>,
+    pub added_edges: Vec<DagEdge>,
+    pub removed_edges: Vec<DagEdge>,
+    pub reselected_edges: Vec<DagEdge>,
+    pub color_changes: Vec<ColorChange>,
+}
+
+impl GraphDiff {
+    pub fn new(old: &DagGraph, new: &DagGraph) -> Self {
+        let old_nodes: HashMap<_, _> = old.nodes.iter().map(|n| (n.id, n)).collect();
+        let new_nodes: HashMap<_, _> = new.nodes.iter().map(|n| (n.id, n)).collect();
+        let old_edges: HashMap<_, _> = old.edges.iter().map(|e| ((e.from, e.to), e)).collect();
+        let new_edges: HashMap<_, _> = new.edges.iter().map(|e| ((e.from, e.to), e)).collect();
+
+        let mut diff = GraphDiff::default();
+        for node in &new.nodes {
+            match old_nodes.get(&node.id) {
+                None => diff.added_nodes.push(*node),
+                Some(prev) if prev.color != node.color => diff.color_changes.push(ColorChange {
+                    id: node.id,
+                    from: prev.color,
+                    to: node.color,
+                }),
+                Some(_) => {}
+            }
+        }
+        diff.removed_nodes = old
+            .nodes
+            .iter()
+            .filter(|n| !new_nodes.contains_key(&n.id))
+            .map(|n| n.id)
+            .collect();
+
+        for edge in &new.edges {
+            match old_edges.get(&(edge.from, edge.to)) {
+                None => diff.added_edges.push(edge.clone()),
+                Some(prev) if prev.is_selected != edge.is_selected => {
+                    diff.reselected_edges.push(edge.clone())
+                }
+                Some(_) => {}
+            }
+        }
+        diff.removed_edges = old
+            .edges
+            .iter()
+            .filter(|e| !new_edges.contains_key(&(e.from, e.to)))
+            .cloned()
+            .collect();
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added_nodes.is_empty()
+            && self.removed_nodes.is_empty()
+            && self.added_edges.is_empty()
+            && self.removed_edges.is_empty()
+            && self.reselected_edges.is_empty()
+            && self.color_changes.is_empty()
+    }
+
+    /// Color changes from `from` to `to`, e.g. blue to red for regression checks.
+    pub fn recolored(&self, from: NodeColor, to: NodeColor) -> impl Iterator<Item = &ColorChange> {
+        self.color_changes
+            .iter()
+            .filter(move |c| c.from == from && c.to == to)
+    }
+}
+
+#[tokio::test]
+async fn test_graph_diff() -> anyhow::Result<()> {
+    use crate::chain_reader::mock::MockChainReader;
+
+    let builder = MockChainReader::builder();
+    let old = builder.generate().await?;
+    let mut new = builder.generate().await?;
+    assert!(GraphDiff::new(&old, &new).is_empty());
+
+    let dropped = new.nodes.remove(0).id;
+    new.edges.retain(|e| e.from != dropped && e.to != dropped);
+    let red = new
+        .nodes
+        .iter_mut()
+        .find(|n| n.color == NodeColor::Red)
+        .expect("mock view has a red block");
+    red.color = NodeColor::Blue;
+    let red = red.id;
+
+    let diff = GraphDiff::new(&old, &new);
+    assert_eq!(diff.removed_nodes, vec![dropped]);
+    assert!(diff.added_nodes.is_empty());
+    assert_eq!(diff.removed_edges.len(), old.edges.len() - new.edges.len());
+    assert_eq!(diff.recolored(NodeColor::Red, NodeColor::Blue).count(), 1);
+    assert_eq!(diff.color_changes[0].id, red);
+    assert_eq!(diff.recolored(NodeColor::Blue, NodeColor::Red).count(), 0);
+
+    let reverse = GraphDiff::new(&new, &old);
+    assert_eq!(reverse.added_nodes.len(), 1);
+    assert_eq!(reverse.added_edges.len(), diff.removed_edges.len());
+    Ok(())
+}
@@ -1,27 +1,250 @@
-use crate::chain_reader::{ChainReader,BlockWindow};
-use crate::dag_graph::{DagEdge, DagNode};
-use serde::Serialize;
+use crate::chain_reader::{ChainReader,BlockWindow,CrawlLimits};
+use crate::dag_graph::layout::{layered_layout, LayerBy};
+use crate::dag_graph::{DagEdge, DagNode, DagStats, VirtualBlock};
+use serde::{Deserialize, Serialize};
+use starcoin_crypto::HashValue;
 use std::sync::Arc;
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct DagGraph {
     pub nodes: Vec<DagNode>,
     pub edges: Vec<DagEdge>,
+    /// Blocks referenced by the view that the reader could not return.
+    #[serde(default)]
+    pub missing: Vec<HashValue>,
+    /// Set when the crawl hit `CrawlLimits::max_nodes`.
+    #[serde(default)]
+    pub truncated: bool,
+    #[serde(default)]
+    pub tips: Vec<HashValue>,
+    #[serde(default)]
+    pub virtual_block: Option<VirtualBlock>,
 }
 
 #[derive(Clone)]
 pub struct DagGraphBuilder {
-    reader: Arc<dyn ChainReader>,
-    window: BlockWindow,
+    pub(super) reader: Arc<dyn ChainReader>,
+    pub(super) window: BlockWindow,
+    pub(super) limits: CrawlLimits,
+    pub(super) stub_edges: bool,
+    layer_by: LayerBy,
+    layout_origin: Option<u64>,
+    bodies: bool,
+    // Selected chain number the window ends at, clamped to the selected tip.
+    tip: Option<u64>,
 }
 impl DagGraphBuilder {
     pub fn new(reader: Arc<dyn ChainReader>, window: BlockWindow) -> Self {
-        Self { reader, window }
+        Self {
+            reader,
+            window,
+            limits: CrawlLimits::default(),
+            stub_edges: false,
+            layer_by: LayerBy::default(),
+            layout_origin: None,
+            bodies: false,
+            tip: None,
+        }
+    }
+
+    pub fn reader(&self) -> Arc<dyn ChainReader> {
+        self.reader.clone()
+    }
+
+    pub fn with_window(mut self, window: BlockWindow) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// Ends the window at selected chain block `number`, keeping its block count,
+    /// to see the DAG as it was when that block was the tip. A number past the
+    /// selected tip ends it at the tip.
+    pub fn with_tip(mut self, number: u64) -> Self {
+        self.tip = Some(number);
+        self
+    }
+
+    // The window to crawl, ended at the tip set by `with_tip` if any.
+    pub(super) async fn window(&self) -> anyhow::Result<BlockWindow> {
+        let Some(number) = self.tip else {
+            return Ok(self.window);
+        };
+        let Some(tip) = self.reader.selected_tip().await? else {
+            return Ok(BlockWindow::Between { from: 0, count: 0 });
+        };
+        let number = number.min(tip.number);
+        let count = match self.window {
+            BlockWindow::Latest(count) | BlockWindow::Between { count, .. } => count,
+        }
+        .min(number + 1);
+        Ok(BlockWindow::Between {
+            from: number - count.saturating_sub(1),
+            count,
+        })
+    }
+
+    pub fn with_max_nodes(mut self, max_nodes: usize) -> Self {
+        self.limits.max_nodes = max_nodes;
+        self
+    }
+
+    /// Also emit edges to parents outside the view; off by default since
+    /// such edges point at nodes that are not part of the graph.
+    pub fn with_stub_edges(mut self, enabled: bool) -> Self {
+        self.stub_edges = enabled;
+        self
+    }
+
+    pub fn with_layer_by(mut self, layer_by: LayerBy) -> Self {
+        self.layer_by = layer_by;
+        self
+    }
+
+    /// Layer drawn at the left margin, 0 if `None`; keep it fixed across polls
+    /// so blocks keep their positions.
+    pub fn with_layout_origin(mut self, origin: Option<u64>) -> Self {
+        self.layout_origin = origin;
+        self
+    }
+
+    /// Also fetch block bodies and fill in transaction counts and gas; off by
+    /// default since it costs a request per block.
+    pub fn with_bodies(mut self, enabled: bool) -> Self {
+        self.bodies = enabled;
+        self
     }
 
     pub async fn generate(&self) -> anyhow::Result<DagGraph> {
-        let provider = self.reader.dag_view(self.window).await?;
+        let provider = self.reader.dag_view(self.window().await?, self.limits).await?;
         let nodes = provider.dag_nodes();
-        let edges = provider.dag_edges();
-        Ok(DagGraph { nodes, edges })
-    }    
+        let mut edges = provider.dag_edges();
+        if self.stub_edges {
+            edges.extend(provider.dag_stub_edges());
+        }
+        let mut graph = DagGraph {
+            nodes,
+            edges,
+            missing: provider.missing_blocks(),
+            truncated: provider.is_truncated(),
+            tips: provider.dag_tips(),
+            virtual_block: provider.virtual_block(),
+        };
+        self.fill_bodies(&mut graph.nodes).await?;
+        let layout = layered_layout(&graph, self.layer_by, self.layout_origin);
+        for node in graph.nodes.iter_mut() {
+            node.position = layout.positions.get(&node.id).copied();
+        }
+        Ok(graph)
+    }
+
+    /// Health statistics over the view, without building the graph.
+    pub async fn stats(&self) -> anyhow::Result<DagStats> {
+        let limits = self.limits.without_side_ghostdag();
+        let provider = self.reader.dag_view(self.window().await?, limits).await?;
+        Ok(provider.dag_stats())
+    }
+
+    /// Blocks of the view with `id` among their parents, without laying it out.
+    pub async fn children(&self, id: HashValue) -> anyhow::Result<Vec<HashValue>> {
+        let limits = self.limits.without_side_ghostdag();
+        let provider = self.reader.dag_view(self.window().await?, limits).await?;
+        let edges = provider.dag_edges().into_iter().chain(provider.dag_stub_edges());
+        Ok(edges.filter(|e| e.from == id).map(|e| e.to).collect())
+    }
+
+    // Transaction counts and gas of `nodes`, if bodies are enabled.
+    pub(super) async fn fill_bodies(&self, nodes: &mut [DagNode]) -> anyhow::Result<()> {
+        if !self.bodies {
+            return Ok(());
+        }
+        let ids: Vec<_> = nodes.iter().map(|n| n.id).collect();
+        let bodies = self.reader.block_bodies(&ids).await?;
+        for (node, body) in nodes.iter_mut().zip(bodies) {
+            if let Some(body) = body {
+                node.txn_count = Some(body.transactions.len());
+                node.gas_used = Some(body.gas_used);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_bodies_are_optional() -> anyhow::Result<()> {
+    use crate::chain_reader::mock::MockChainReader;
+
+    let builder = MockChainReader::builder();
+    let graph = builder.generate().await?;
+    assert!(graph.nodes.iter().all(|n| n.txn_count.is_none()));
+
+    let graph = builder.with_bodies(true).generate().await?;
+    let red = graph
+        .nodes
+        .iter()
+        .find(|n| n.color == crate::dag_graph::NodeColor::Red)
+        .unwrap();
+    assert_eq!(red.txn_count, Some(2));
+    assert_eq!(red.gas_used, Some(2_000));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_view_as_of_past_tip() -> anyhow::Result<()> {
+    use crate::chain_reader::mock::MockChainReader;
+
+    let builder = MockChainReader::builder();
+    let graph = builder.with_tip(4).generate().await?;
+    assert!(graph.nodes.iter().all(|n| n.score <= 4));
+    let tip = graph.nodes.iter().find(|n| n.score == 4).expect("tip is in the view");
+    assert_eq!(graph.tips, vec![tip.id]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_view_as_of_out_of_range_tip() -> anyhow::Result<()> {
+    use crate::chain_reader::mock::MockChainReader;
+
+    let builder = MockChainReader::builder();
+    let graph = builder.clone().with_tip(u64::MAX).generate().await?;
+    assert_eq!(graph.nodes.len(), builder.generate().await?.nodes.len());
+    let empty = builder.clone().with_window(BlockWindow::Latest(0)).with_tip(u64::MAX);
+    assert!(empty.generate().await?.nodes.is_empty());
+
+    // The window ends at the selected tip before anything is asked of the node.
+    let BlockWindow::Between { from, count } = builder.with_tip(u64::MAX).window().await? else {
+        panic!("a tip makes a bounded window");
+    };
+    assert_eq!((from, count), (4, 4));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_children_match_the_generated_view() -> anyhow::Result<()> {
+    use crate::chain_reader::mock::MockChainReader;
+
+    let builder = MockChainReader::builder();
+    let graph = builder.generate().await?;
+    for node in &graph.nodes {
+        let edges = graph.edges.iter().filter(|e| e.from == node.id);
+        let mut expected: Vec<_> = edges.map(|e| e.to).collect();
+        let mut children = builder.children(node.id).await?;
+        expected.sort();
+        children.sort();
+        assert_eq!(children, expected);
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_graph_without_optional_fields_deserializes() -> anyhow::Result<()> {
+    use crate::chain_reader::mock::MockChainReader;
+
+    // Graphs saved or posted before these fields existed carry only nodes and edges.
+    let builder = MockChainReader::builder();
+    let graph = builder.generate().await?;
+    let json = serde_json::json!({ "nodes": graph.nodes, "edges": graph.edges });
+    let parsed: DagGraph = serde_json::from_value(json)?;
+    assert_eq!(parsed.nodes.len(), graph.nodes.len());
+    assert!(parsed.missing.is_empty() && parsed.tips.is_empty());
+    assert!(!parsed.truncated && parsed.virtual_block.is_none());
+    Ok(())
 }
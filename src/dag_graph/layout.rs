@@ -0,0 +1,264 @@
+use crate::dag_graph::DagGraph;
+use serde::{Deserialize, Serialize};
+use starcoin_crypto::HashValue;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Horizontal distance between consecutive layers.
+pub const LAYER_SPACING: f64 = 140.0;
+/// Vertical distance between blocks of the same layer.
+pub const ROW_SPACING: f64 = 50.0;
+/// Blank border around the drawing.
+pub const MARGIN: f64 = 60.0;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct Position {
+    pub x: f64,
+    pub y: f64,
+}
+
+/// What decides a block's layer, i.e. its column in the drawing.
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LayerBy {
+    #[default]
+    Height,
+    BlueScore,
+}
+
+/// Node centers of a drawing that flows from parents on the left to children on the right.
+#[derive(Debug, Default)]
+pub struct Layout {
+    pub positions: HashMap<HashValue, Position>,
+    /// Left edge of the drawn area; positions need not start near zero.
+    pub left: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl Layout {
+    /// The positions already carried by `graph`'s nodes, or a fresh layered
+    /// layout by height if some node has none.
+    pub fn of(graph: &DagGraph) -> Layout {
+        let positions: Option<HashMap<_, _>> = graph
+            .nodes
+            .iter()
+            .map(|n| Some((n.id, n.position?)))
+            .collect();
+        match positions {
+            Some(positions) => Self::with_bounds(positions),
+            None => layered_layout(graph, LayerBy::Height, None),
+        }
+    }
+
+    fn with_bounds(positions: HashMap<HashValue, Position>) -> Layout {
+        let left = positions
+            .values()
+            .map(|p| p.x - MARGIN)
+            .reduce(f64::min)
+            .unwrap_or(0.0);
+        let right = positions.values().map(|p| p.x).fold(left, f64::max) + MARGIN;
+        let height = positions.values().map(|p| p.y).fold(0.0, f64::max) + MARGIN;
+        Layout {
+            positions,
+            left,
+            width: right - left,
+            height,
+        }
+    }
+}
+
+/// Lays `graph` out in layers, deterministically for a given graph.
+///
+/// The selected chain (the blocks joined by selected-parent edges) runs along the
+/// top row as a straight spine. The other blocks of each layer fill the rows below
+/// it by hash, so a block's row depends only on the blocks of its own layer and
+/// stays put as the window slides. Ordering rows to reduce edge crossings would
+/// tie them to the neighbouring layers, which change at the edges of the window.
+///
+/// A block's x follows from its layer alone: layer `origin`, 0 if omitted, sits at
+/// the left margin, so a block keeps its x across views with the same origin.
+///
+/// With `LayerBy::BlueScore`, a block without ghostdag data is placed one layer
+/// after its highest parent.
+pub fn layered_layout(graph: &DagGraph, layer_by: LayerBy, origin: Option<u64>) -> Layout {
+    let mut parents: HashMap<_, Vec<_>> = HashMap::new();
+    let mut spine = HashSet::new();
+    let in_view: HashSet<_> = graph.nodes.iter().map(|n| n.id).collect();
+    for edge in graph
+        .edges
+        .iter()
+        .filter(|e| in_view.contains(&e.from) && in_view.contains(&e.to))
+    {
+        parents.entry(edge.to).or_default().push(edge.from);
+        if edge.is_selected {
+            spine.extend([edge.from, edge.to]);
+        }
+    }
+
+    // Nodes come parents first, so a parent's layer is known before its children's.
+    let mut layer_of: HashMap<HashValue, u64> = HashMap::new();
+    for node in &graph.nodes {
+        let layer = match layer_by {
+            LayerBy::Height => node.score,
+            LayerBy::BlueScore => node.blue_score.unwrap_or_else(|| {
+                parents
+                    .get(&node.id)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|p| layer_of.get(p))
+                    .max()
+                    .map_or(0, |l| l + 1)
+            }),
+        };
+        layer_of.insert(node.id, layer);
+    }
+
+    let mut layers: BTreeMap<u64, Vec<HashValue>> = BTreeMap::new();
+    for (id, layer) in &layer_of {
+        layers.entry(*layer).or_default().push(*id);
+    }
+    let origin = origin.unwrap_or(0);
+    let keys: Vec<u64> = layers.keys().copied().collect();
+    let mut layers: Vec<Vec<HashValue>> = layers.into_values().collect();
+    for layer in layers.iter_mut() {
+        layer.sort_by_key(|id| (!spine.contains(id), *id));
+    }
+
+    let mut positions = HashMap::new();
+    for (key, layer) in keys.iter().zip(&layers) {
+        let x = MARGIN + (*key as f64 - origin as f64) * LAYER_SPACING;
+        // Layers without a chain block leave the spine row empty.
+        let offset = usize::from(!layer.first().is_some_and(|id| spine.contains(id)));
+        for (i, id) in layer.iter().enumerate() {
+            positions.insert(
+                *id,
+                Position {
+                    x,
+                    y: MARGIN + (i + offset) as f64 * ROW_SPACING,
+                },
+            );
+        }
+    }
+    Layout::with_bounds(positions)
+}
+
+#[tokio::test]
+async fn test_layered_layout() -> anyhow::Result<()> {
+    use crate::chain_reader::mock::MockChainReader;
+
+    let builder = MockChainReader::builder();
+    let graph = builder.generate().await?;
+
+    for layer_by in [LayerBy::Height, LayerBy::BlueScore] {
+        let layout = layered_layout(&graph, layer_by, None);
+        assert_eq!(layout.positions, layered_layout(&graph, layer_by, None).positions);
+        assert_eq!(layout.positions.len(), graph.nodes.len());
+
+        let distinct: HashSet<_> = layout
+            .positions
+            .values()
+            .map(|p| (p.x as i64, p.y as i64))
+            .collect();
+        assert_eq!(distinct.len(), graph.nodes.len());
+
+        for edge in graph.edges.iter().filter(|e| e.is_selected) {
+            assert_eq!(layout.positions[&edge.from].y, MARGIN);
+            assert_eq!(layout.positions[&edge.to].y, MARGIN);
+        }
+    }
+
+    let node = &graph.nodes[0];
+    assert_eq!(node.position, Some(Layout::of(&graph).positions[&node.id]));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_layout_is_stable_across_windows() -> anyhow::Result<()> {
+    use crate::chain_reader::{mock::MockChainReader, BlockWindow};
+
+    // The wider view reaches one layer further back.
+    let builder = MockChainReader::builder().with_window(BlockWindow::Latest(2));
+    let older = builder.clone().with_window(BlockWindow::Latest(3)).generate().await?;
+    let newer = builder.generate().await?;
+
+    for origin in [None, Some(2)] {
+        let older = layered_layout(&older, LayerBy::Height, origin);
+        let newer = layered_layout(&newer, LayerBy::Height, origin);
+        let shared: Vec<_> = older
+            .positions
+            .keys()
+            .filter(|id| newer.positions.contains_key(id))
+            .collect();
+        assert!(shared.len() > 1);
+        for id in shared {
+            assert_eq!(older.positions[id], newer.positions[id]);
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_rows_survive_a_slide() {
+    use crate::chain_reader::ext::{BlockHeader, DagBuildContext, GhostdagData};
+    use crate::dag_graph::DagGraphProvider;
+
+    let header = |number, parents_hash| BlockHeader {
+        id: HashValue::random(),
+        number,
+        timestamp: 0,
+        parents_hash,
+    };
+    for _ in 0..20 {
+        // Two side blocks per layer, each on the other's predecessor, so rows
+        // ordered by their parents would depend on how far back the view reaches.
+        // Hashes are random, so the shape is tried a few times.
+        let mut chain = vec![header(0, vec![])];
+        let mut side = vec![header(0, vec![]), header(0, vec![])];
+        for number in 1..6 {
+            let (a, b) = (side[side.len() - 2].id, side[side.len() - 1].id);
+            chain.push(header(number, vec![chain[chain.len() - 1].id]));
+            side.extend([header(number, vec![b]), header(number, vec![a])]);
+        }
+        let ghostdag_map: HashMap<_, _> = chain
+            .windows(2)
+            .map(|pair| {
+                let gd = GhostdagData {
+                    blue_score: pair[1].number,
+                    blue_work: pair[1].number as u128,
+                    selected_parent: pair[0].id,
+                    mergeset_blues: vec![pair[0].id],
+                    mergeset_reds: vec![],
+                    blues_anticone_sizes: BTreeMap::new(),
+                };
+                (pair[1].id, gd)
+            })
+            .collect();
+        let layout_from = |lowest: u64| {
+            let ctx = DagBuildContext {
+                header_map: chain
+                    .iter()
+                    .chain(&side)
+                    .filter(|h| h.number >= lowest)
+                    .map(|h| (h.id, h.clone()))
+                    .collect(),
+                ghostdag_map: ghostdag_map.clone(),
+                ..Default::default()
+            };
+            let graph = DagGraph {
+                nodes: ctx.dag_nodes(),
+                edges: ctx.dag_edges(),
+                missing: Vec::new(),
+                truncated: false,
+                tips: Vec::new(),
+                virtual_block: None,
+            };
+            layered_layout(&graph, LayerBy::Height, None)
+        };
+
+        let (wide, slid) = (layout_from(0), layout_from(3));
+        assert_eq!(slid.positions.len(), 9);
+        for (id, position) in &slid.positions {
+            assert_eq!(wide.positions[id], *position);
+        }
+    }
+}
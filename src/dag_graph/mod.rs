@@ -1,21 +1,43 @@
-use serde::Serialize;
+use crate::chain_reader::ext::ChainBlock;
+use serde::{Deserialize, Serialize};
+use layout::Position;
 use starcoin_crypto::HashValue;
+use std::collections::BTreeMap;
 
-#[derive(Debug, Serialize, Clone, Copy)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
 pub struct DagNode {
     pub id: HashValue,
     pub score: u64,
+    pub blue_score: Option<u64>,
+    /// Block timestamp in milliseconds since the Unix epoch.
+    pub timestamp: u64,
     pub color: NodeColor,
+    /// The selected chain block whose mergeset contains this node.
+    pub merged_by: Option<HashValue>,
+    /// Position of this node in `merged_by`'s mergeset, blues first then reds.
+    pub mergeset_index: Option<usize>,
+    /// Set when some parents of this node lie outside the view, so it is not a real root.
+    pub is_boundary: bool,
+    /// Number of parents that were not fetched into the view.
+    pub omitted_parents: usize,
+    /// Number of user transactions, when bodies were fetched.
+    pub txn_count: Option<usize>,
+    /// Gas used by the block, when bodies were fetched.
+    pub gas_used: Option<u64>,
+    /// Drawing position, filled in by `DagGraphBuilder`.
+    pub position: Option<Position>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct DagEdge {
     pub from: HashValue,
     pub to: HashValue,
     pub is_selected: bool,
+    /// Set when `from` is outside the view and has no node of its own.
+    pub is_stub: bool,
 }
 
-#[derive(Debug, Serialize, Clone, Copy)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum NodeColor {
     Blue,
@@ -23,10 +45,87 @@ pub enum NodeColor {
     Unknown,
 }
 
+/// The block that would be mined on top of every tip of the view.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct VirtualBlock {
+    pub parents: Vec<HashValue>,
+    pub selected_parent: HashValue,
+    /// Selected parent first, then the rest of the mergeset by ascending blue
+    /// work, a topological order.
+    pub mergeset: Vec<HashValue>,
+}
+
+/// Health figures over the blocks of a view. Distributions map a size to the
+/// number of blocks with that size.
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+pub struct DagStats {
+    pub blocks: usize,
+    pub selected_chain_blocks: usize,
+    /// Blocks without children in the view.
+    pub tips: usize,
+    pub tip_number: Option<u64>,
+    pub tip_blue_score: Option<u64>,
+    /// Milliseconds between the oldest and newest block timestamps.
+    pub time_span: u64,
+    pub blocks_per_second: Option<f64>,
+    /// Selected chain blocks added per second.
+    pub chain_growth_rate: Option<f64>,
+    /// Mean number of blocks sharing a number.
+    pub average_width: f64,
+    pub max_width: usize,
+    pub parents_per_block: BTreeMap<usize, usize>,
+    /// Mergeset sizes of the selected chain blocks.
+    pub mergeset_sizes: BTreeMap<usize, usize>,
+    /// Share of red blocks in the selected chain blocks' mergesets.
+    pub red_ratio: Option<f64>,
+}
+
+/// Source of a DAG view.
+///
+/// Output must be deterministic for the same blocks: `dag_nodes` lists parents
+/// before children with ties broken by blue work, number and hash, and edge lists
+/// are grouped by child in node order, then sorted by parent hash.
 pub trait DagGraphProvider {
     fn dag_nodes(&self) -> Vec<DagNode>;
     fn dag_edges(&self) -> Vec<DagEdge>;
+    /// Edges from nodes in the view to parents that were not fetched.
+    fn dag_stub_edges(&self) -> Vec<DagEdge> {
+        Vec::new()
+    }
+    /// Blocks that were referenced but could not be fetched, sorted by hash.
+    fn missing_blocks(&self) -> Vec<HashValue> {
+        Vec::new()
+    }
+    /// Whether the view was cut short by a crawl limit.
+    fn is_truncated(&self) -> bool {
+        false
+    }
+    /// Blocks in the view that have no children in the view.
+    fn dag_tips(&self) -> Vec<HashValue> {
+        Vec::new()
+    }
+    fn virtual_block(&self) -> Option<VirtualBlock> {
+        None
+    }
+    /// Selected chain blocks of the view, oldest first.
+    fn chain_blocks(&self) -> Vec<ChainBlock> {
+        Vec::new()
+    }
+    fn dag_stats(&self) -> DagStats {
+        DagStats::default()
+    }
 }
 
+pub mod diff;
+pub use diff::GraphDiff;
 mod graph;
-pub use graph::DagGraphBuilder;
+pub use graph::{DagGraph, DagGraphBuilder};
+pub mod layout;
+pub mod reachability;
+pub use reachability::{Reachability, ReachabilityBlock};
+pub mod reachability_index;
+pub use reachability_index::ReachabilityIndex;
+pub mod stream;
+pub use stream::GraphItem;
+pub mod trace;
+pub use trace::TxnTrace;
@@ -0,0 +1,306 @@
+use crate::chain_reader::ext::DagBuildContext;
+use crate::dag_graph::reachability_index::ReachabilityIndex;
+use crate::dag_graph::DagGraph;
+use crate::follower::{FollowerSink, Observation};
+use async_trait::async_trait;
+use starcoin_crypto::HashValue;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+
+/// Default number of blocks kept by a follower-fed [`Reachability`].
+pub const DEFAULT_CAPACITY: usize = 200_000;
+
+/// A block with the links the reachability index needs.
+#[derive(Debug, Clone)]
+pub struct ReachabilityBlock {
+    pub id: HashValue,
+    pub number: u64,
+    pub parents: Vec<HashValue>,
+    pub selected_parent: Option<HashValue>,
+}
+
+/// Ancestry queries over the blocks added so far, either a single view at once
+/// or, fed by the chain follower, every block it has seen.
+///
+/// Relations through blocks that were never added are invisible, so two blocks
+/// whose only connecting path leaves the indexed blocks are reported as being in
+/// each other's anticone. Pairwise checks go through a [`ReachabilityIndex`] and
+/// take O(log n). Window-bounded queries rely on a block's number being above
+/// its parents'.
+pub struct Reachability {
+    index: ReachabilityIndex,
+    numbers: HashMap<HashValue, u64>,
+    by_number: BTreeMap<u64, Vec<HashValue>>,
+    capacity: usize,
+}
+
+impl Default for Reachability {
+    fn default() -> Self {
+        Self::with_capacity(DEFAULT_CAPACITY)
+    }
+}
+
+impl Reachability {
+    /// Indexes `blocks`, in any order. Parents that are not themselves listed
+    /// are ignored.
+    pub fn new(blocks: impl IntoIterator<Item = ReachabilityBlock>) -> Self {
+        let mut blocks: Vec<_> = blocks.into_iter().collect();
+        // Mostly parents first, so few blocks wait for a parent to be linked.
+        blocks.sort_by_key(|b| b.number);
+        let mut reach = Self::with_capacity(usize::MAX);
+        for block in &blocks {
+            reach.add_block(block);
+        }
+        reach
+    }
+
+    /// An empty index that keeps up to `capacity` blocks, evicting the lowest
+    /// numbers first.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            index: ReachabilityIndex::new(),
+            numbers: HashMap::new(),
+            by_number: BTreeMap::new(),
+            capacity,
+        }
+    }
+
+    /// Adds `block`, linking it to indexed parents and children whatever order
+    /// they arrive in. Adding a block twice is a no-op.
+    pub fn add_block(&mut self, block: &ReachabilityBlock) {
+        if self.index.contains(&block.id) {
+            return;
+        }
+        self.index
+            .add_block(block.id, &block.parents, block.selected_parent);
+        self.numbers.insert(block.id, block.number);
+        self.by_number.entry(block.number).or_default().push(block.id);
+        while self.index.len() > self.capacity {
+            self.evict_oldest();
+        }
+    }
+
+    // Evicts the blocks with the lowest number, along with anything left in their
+    // past, which the index requires to go with them.
+    fn evict_oldest(&mut self) {
+        let Some((_, oldest)) = self.by_number.pop_first() else {
+            return;
+        };
+        let mut evicted: HashSet<_> = oldest.iter().copied().collect();
+        for id in &oldest {
+            evicted.extend(self.index.past(id));
+        }
+        self.index.remove(&evicted);
+        for id in &evicted {
+            let Some(number) = self.numbers.remove(id) else {
+                continue;
+            };
+            if let Some(ids) = self.by_number.get_mut(&number) {
+                ids.retain(|b| b != id);
+                if ids.is_empty() {
+                    self.by_number.remove(&number);
+                }
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    pub fn contains(&self, id: &HashValue) -> bool {
+        self.index.contains(id)
+    }
+
+    /// Whether `ancestor` is in the past of `descendant`. A block is not its own ancestor.
+    pub fn is_ancestor(&self, ancestor: &HashValue, descendant: &HashValue) -> bool {
+        self.index.is_dag_ancestor_of(ancestor, descendant)
+    }
+
+    /// Indexed blocks with `id` among their parents.
+    pub fn children(&self, id: &HashValue) -> Vec<HashValue> {
+        self.index.children(id)
+    }
+
+    /// All indexed ancestors of `id`, excluding `id` itself.
+    pub fn past(&self, id: &HashValue) -> HashSet<HashValue> {
+        self.index.past(id)
+    }
+
+    /// All indexed descendants of `id`, excluding `id` itself.
+    pub fn future(&self, id: &HashValue) -> HashSet<HashValue> {
+        self.index.future(id)
+    }
+
+    /// Indexed blocks that are neither in the past nor in the future of `id`.
+    pub fn anticone(&self, id: &HashValue) -> HashSet<HashValue> {
+        self.index.anticone(id)
+    }
+
+    /// Ancestors of `id` numbered at most `window` below it.
+    pub fn past_within(&self, id: &HashValue, window: u64) -> HashSet<HashValue> {
+        let Some(floor) = self.numbers.get(id).map(|n| n.saturating_sub(window)) else {
+            return HashSet::new();
+        };
+        self.index.past_where(id, |b| self.numbers[b] >= floor)
+    }
+
+    /// Descendants of `id` numbered at most `window` above it.
+    pub fn future_within(&self, id: &HashValue, window: u64) -> HashSet<HashValue> {
+        let Some(ceiling) = self.numbers.get(id).map(|n| n.saturating_add(window)) else {
+            return HashSet::new();
+        };
+        self.index.future_where(id, |b| self.numbers[b] <= ceiling)
+    }
+
+    /// Blocks of the anticone of `id` numbered within `window` of it. Only the
+    /// blocks in that range are checked.
+    pub fn anticone_within(&self, id: &HashValue, window: u64) -> HashSet<HashValue> {
+        let Some(number) = self.numbers.get(id) else {
+            return HashSet::new();
+        };
+        let range = number.saturating_sub(window)..=number.saturating_add(window);
+        self.by_number
+            .range(range)
+            .flat_map(|(_, ids)| ids)
+            .filter(|b| *b != id && !self.is_ancestor(b, id) && !self.is_ancestor(id, b))
+            .copied()
+            .collect()
+    }
+}
+
+/// Adds the blocks of every poll, so the index grows with the chain and drops
+/// its oldest blocks once full.
+#[async_trait]
+impl FollowerSink for Arc<RwLock<Reachability>> {
+    async fn observe(&mut self, obs: &Observation) {
+        let mut reach = self.write().unwrap();
+        for block in &obs.blocks {
+            reach.add_block(block);
+        }
+    }
+}
+
+impl From<&DagBuildContext> for Reachability {
+    fn from(ctx: &DagBuildContext) -> Self {
+        Self::new(ctx.header_map.iter().map(|(id, header)| ReachabilityBlock {
+            id: *id,
+            number: header.number,
+            parents: header.parents_hash(),
+            selected_parent: ctx.ghostdag_map.get(id).map(|gd| gd.selected_parent),
+        }))
+    }
+}
+
+impl From<&DagGraph> for Reachability {
+    fn from(graph: &DagGraph) -> Self {
+        let mut blocks: HashMap<_, _> = graph
+            .nodes
+            .iter()
+            .map(|n| {
+                let block = ReachabilityBlock {
+                    id: n.id,
+                    number: n.score,
+                    parents: Vec::new(),
+                    selected_parent: None,
+                };
+                (n.id, block)
+            })
+            .collect();
+        for edge in &graph.edges {
+            if let Some(block) = blocks.get_mut(&edge.to) {
+                block.parents.push(edge.from);
+                if edge.is_selected {
+                    block.selected_parent = Some(edge.from);
+                }
+            }
+        }
+        Self::new(blocks.into_values())
+    }
+}
+
+#[cfg(test)]
+fn block(id: HashValue, number: u64, parents: Vec<HashValue>) -> ReachabilityBlock {
+    ReachabilityBlock {
+        id,
+        number,
+        parents,
+        selected_parent: None,
+    }
+}
+
+#[test]
+fn test_past_future_anticone() {
+    // a <- b <- d, a <- c
+    let [a, b, c, d] = [(); 4].map(|_| HashValue::random());
+    let reach = Reachability::new([
+        block(a, 0, vec![]),
+        block(b, 1, vec![a]),
+        block(c, 1, vec![a]),
+        block(d, 2, vec![b, HashValue::random()]),
+    ]);
+    assert!(reach.is_ancestor(&a, &d));
+    assert!(!reach.is_ancestor(&d, &a));
+    assert!(!reach.is_ancestor(&a, &a));
+    assert_eq!(reach.past(&d), [a, b].into());
+    assert_eq!(reach.future(&a), [b, c, d].into());
+    assert_eq!(reach.anticone(&c), [b, d].into());
+    assert!(reach.anticone(&a).is_empty());
+
+    assert_eq!(reach.past_within(&d, 1), [b].into());
+    assert_eq!(reach.future_within(&a, 1), [b, c].into());
+    assert_eq!(reach.anticone_within(&c, 0), [b].into());
+}
+
+#[test]
+fn test_capacity_evicts_oldest() {
+    // A chain with a side block merged by the tip: a <- b <- c <- e, a <- s <- e
+    let [a, b, c, s, e] = [(); 5].map(|_| HashValue::random());
+    let mut reach = Reachability::with_capacity(4);
+    for block in [
+        block(a, 0, vec![]),
+        block(b, 1, vec![a]),
+        block(s, 1, vec![a]),
+        block(c, 2, vec![b]),
+    ] {
+        reach.add_block(&block);
+    }
+    assert_eq!(reach.len(), 4);
+    reach.add_block(&block(e, 3, vec![c, s]));
+
+    assert_eq!(reach.len(), 4);
+    assert!(!reach.contains(&a));
+    assert!(reach.is_ancestor(&b, &e) && reach.is_ancestor(&s, &e));
+    assert_eq!(reach.anticone(&s), [b, c].into());
+}
+#[tokio::test]
+async fn test_follower_feeds_reachability() -> anyhow::Result<()> {
+    use crate::chain_reader::mock::MockChainReader;
+    use crate::follower::ChainFollower;
+
+    let mut follower = ChainFollower::new(Arc::new(MockChainReader::new()), 4);
+    let obs = follower.poll().await?;
+    // An earlier poll that saw only the newer half of the blocks, so the older
+    // half arrives after its children.
+    let mut earlier = obs.clone();
+    earlier.blocks.drain(..obs.blocks.len() / 2);
+
+    let shared = Arc::new(RwLock::new(Reachability::default()));
+    let mut sink = shared.clone();
+    sink.observe(&earlier).await;
+    assert_eq!(shared.read().unwrap().len(), earlier.blocks.len());
+    sink.observe(&obs).await;
+    sink.observe(&obs).await;
+
+    let reach = shared.read().unwrap();
+    assert_eq!(reach.len(), obs.blocks.len());
+    let (first, tip) = (obs.chain[0].id, obs.chain[obs.chain.len() - 1].id);
+    assert!(reach.is_ancestor(&first, &tip));
+    assert_eq!(reach.past(&tip).len() + 1, obs.blocks.len());
+    assert!(reach.anticone(&tip).is_empty());
+    Ok(())
+}
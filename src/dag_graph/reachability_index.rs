@@ -0,0 +1,613 @@
+use starcoin_crypto::HashValue;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+
+const INTERVAL_START: u64 = 1;
+const INTERVAL_END: u64 = u64::MAX - 1;
+
+// Inclusive range of slots owned by a block of the reachability tree.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Interval {
+    start: u64,
+    end: u64,
+}
+
+impl Interval {
+    fn contains(&self, other: &Interval) -> bool {
+        self.start <= other.start && other.end <= self.end
+    }
+}
+
+struct IndexNode {
+    interval: Interval,
+    // First slot of `interval` not yet handed out to a tree child.
+    next_free: u64,
+    tree_parent: Option<HashValue>,
+    tree_children: Vec<HashValue>,
+    // Parents in the index.
+    parents: Vec<HashValue>,
+    // Parents not in the index, linked once they are added.
+    missing_parents: Vec<HashValue>,
+    // Blocks in the index with this one among their parents.
+    children: Vec<HashValue>,
+    // Blocks in the future of this one that are outside its tree subtree,
+    // kept sorted by interval start and pairwise disjoint.
+    future_covering_set: Vec<HashValue>,
+}
+
+/// Reachability index in the style of the Kaspa/Starcoin reachability service.
+///
+/// Each block gets an interval nested inside its tree parent's (the selected parent
+/// when known), so tree ancestry is interval containment. Ancestry through other
+/// parents is answered by the future covering set: for every block, the minimal set
+/// of blocks in its future whose tree subtrees cover the rest of that future.
+/// Both checks are a containment test plus a binary search, i.e. O(log n).
+/// Past, future and anticone are walks that only visit the blocks they return
+/// and their immediate neighbours, never the whole index.
+///
+/// Blocks are best added parents first. A block added before one of its parents
+/// is linked to that parent once it arrives, and a block whose parents are all
+/// unknown becomes a root.
+#[derive(Default)]
+pub struct ReachabilityIndex {
+    nodes: HashMap<HashValue, IndexNode>,
+    roots: Vec<HashValue>,
+    // Blocks without children in the index.
+    tips: HashSet<HashValue>,
+    // Blocks not in the index, with the indexed children waiting for them.
+    waiting: HashMap<HashValue, Vec<HashValue>>,
+    // Next free slot of the implicit root that holds every real root.
+    next_free: u64,
+}
+
+impl ReachabilityIndex {
+    pub fn new() -> Self {
+        Self {
+            next_free: INTERVAL_START,
+            ..Default::default()
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn contains(&self, id: &HashValue) -> bool {
+        self.nodes.contains_key(id)
+    }
+
+    /// Adds `id` below `parents`. The tree parent is `selected_parent` if it is one of
+    /// the known parents, otherwise the first known parent. Children added earlier
+    /// that named `id` as a parent are linked to it. Adding a block twice is a no-op.
+    pub fn add_block(
+        &mut self,
+        id: HashValue,
+        parents: &[HashValue],
+        selected_parent: Option<HashValue>,
+    ) {
+        if self.nodes.contains_key(&id) {
+            return;
+        }
+        let mut known = Vec::new();
+        let mut missing = Vec::new();
+        for p in parents {
+            if known.contains(p) || missing.contains(p) {
+                continue;
+            }
+            match self.nodes.contains_key(p) {
+                true => known.push(*p),
+                false => missing.push(*p),
+            }
+        }
+        let tree_parent = selected_parent
+            .filter(|p| known.contains(p))
+            .or_else(|| known.first().copied());
+
+        let interval = self.allocate(tree_parent);
+        for p in &missing {
+            self.waiting.entry(*p).or_default().push(id);
+        }
+        self.nodes.insert(
+            id,
+            IndexNode {
+                interval: interval.unwrap_or(Interval { start: 0, end: 0 }),
+                next_free: 0,
+                tree_parent,
+                tree_children: Vec::new(),
+                parents: known.clone(),
+                missing_parents: missing,
+                children: Vec::new(),
+                future_covering_set: Vec::new(),
+            },
+        );
+        for p in &known {
+            self.node_mut(p).children.push(id);
+            self.tips.remove(p);
+        }
+        self.tips.insert(id);
+        match tree_parent {
+            Some(p) => self.node_mut(&p).tree_children.push(id),
+            None => self.roots.push(id),
+        }
+        match interval {
+            Some(interval) => self.node_mut(&id).next_free = interval.start + 1,
+            None => self.reindex(),
+        }
+
+        // The other parents reach `id` outside the tree.
+        let others = known.into_iter().filter(|p| Some(*p) != tree_parent);
+        self.cover(others.collect(), id);
+
+        // Children added earlier get `id` as a parent. Roots among them move
+        // under it in the tree, so its past reaches them without covering sets.
+        let children = self.waiting.remove(&id).unwrap_or_default();
+        let mut adopted = false;
+        for child in &children {
+            let node = self.node_mut(child);
+            node.missing_parents.retain(|p| *p != id);
+            node.parents.push(id);
+            if node.tree_parent.is_none() {
+                node.tree_parent = Some(id);
+                self.roots.retain(|r| r != child);
+                self.node_mut(&id).tree_children.push(*child);
+                adopted = true;
+            }
+            self.node_mut(&id).children.push(*child);
+            self.tips.remove(&id);
+        }
+        if adopted {
+            self.reindex();
+            self.normalize_future_covering_sets();
+        }
+        for child in children {
+            self.cover(vec![id], child);
+        }
+    }
+
+    /// Removes `blocks`, which must hold every indexed block in their past. Their
+    /// remaining children wait for them as missing parents, and tree children
+    /// become roots.
+    pub fn remove(&mut self, blocks: &HashSet<HashValue>) {
+        for id in blocks {
+            let Some(node) = self.nodes.remove(id) else {
+                continue;
+            };
+            self.tips.remove(id);
+            for p in &node.missing_parents {
+                if let Some(children) = self.waiting.get_mut(p) {
+                    children.retain(|c| c != id);
+                    if children.is_empty() {
+                        self.waiting.remove(p);
+                    }
+                }
+            }
+            for child in node.children.iter().filter(|c| !blocks.contains(c)) {
+                let child_node = self.node_mut(child);
+                child_node.parents.retain(|p| p != id);
+                child_node.missing_parents.push(*id);
+                if child_node.tree_parent == Some(*id) {
+                    child_node.tree_parent = None;
+                    self.roots.push(*child);
+                }
+                self.waiting.entry(*id).or_default().push(*child);
+            }
+        }
+        let nodes = &self.nodes;
+        self.roots.retain(|r| nodes.contains_key(r));
+        self.roots.sort_by_key(|r| nodes[r].interval.start);
+    }
+
+    /// Whether `ancestor` is in the past of `descendant`. A block is not its own ancestor.
+    pub fn is_dag_ancestor_of(&self, ancestor: &HashValue, descendant: &HashValue) -> bool {
+        if ancestor == descendant {
+            return false;
+        }
+        let (Some(a), Some(d)) = (self.nodes.get(ancestor), self.nodes.get(descendant)) else {
+            return false;
+        };
+        if a.interval.contains(&d.interval) {
+            return true;
+        }
+        let fcs = &a.future_covering_set;
+        let pos = fcs.partition_point(|c| self.nodes[c].interval.start <= d.interval.start);
+        pos > 0 && self.nodes[&fcs[pos - 1]].interval.contains(&d.interval)
+    }
+
+    /// Whether `ancestor` is `descendant`'s tree parent or one of its tree ancestors.
+    pub fn is_tree_ancestor_of(&self, ancestor: &HashValue, descendant: &HashValue) -> bool {
+        match (self.nodes.get(ancestor), self.nodes.get(descendant)) {
+            (Some(a), Some(d)) => ancestor != descendant && a.interval.contains(&d.interval),
+            _ => false,
+        }
+    }
+
+    /// Indexed blocks with `id` among their parents.
+    pub fn children(&self, id: &HashValue) -> Vec<HashValue> {
+        self.nodes.get(id).map_or_else(Vec::new, |n| n.children.clone())
+    }
+
+    /// Every block in the past of `id`, found by walking parents.
+    pub fn past(&self, id: &HashValue) -> HashSet<HashValue> {
+        self.past_where(id, |_| true)
+    }
+
+    /// Like [`ReachabilityIndex::past`], without walking into blocks `keep`
+    /// rejects. `keep` must reject their past as well.
+    pub fn past_where(&self, id: &HashValue, keep: impl Fn(&HashValue) -> bool) -> HashSet<HashValue> {
+        let mut past = HashSet::new();
+        let mut stack = self.nodes.get(id).map_or_else(Vec::new, |n| n.parents.clone());
+        while let Some(b) = stack.pop() {
+            if keep(&b) && past.insert(b) {
+                stack.extend(self.nodes[&b].parents.iter().copied());
+            }
+        }
+        past
+    }
+
+    /// Every block in the future of `id`: its tree subtree and the tree subtrees
+    /// of its future covering set.
+    pub fn future(&self, id: &HashValue) -> HashSet<HashValue> {
+        self.future_where(id, |_| true)
+    }
+
+    /// Like [`ReachabilityIndex::future`], without walking into blocks `keep`
+    /// rejects. `keep` must reject their future as well.
+    pub fn future_where(
+        &self,
+        id: &HashValue,
+        keep: impl Fn(&HashValue) -> bool,
+    ) -> HashSet<HashValue> {
+        let Some(node) = self.nodes.get(id) else {
+            return HashSet::new();
+        };
+        let mut future = HashSet::new();
+        let mut stack: Vec<_> = node
+            .tree_children
+            .iter()
+            .chain(&node.future_covering_set)
+            .copied()
+            .collect();
+        while let Some(b) = stack.pop() {
+            if keep(&b) && future.insert(b) {
+                stack.extend(self.nodes[&b].tree_children.iter().copied());
+            }
+        }
+        future
+    }
+
+    /// Every block neither in the past nor in the future of `id`. Walks parents
+    /// down from the tips and stops at the past of `id`, which holds no anticone
+    /// block, so only the future and anticone of `id` and their parents are visited.
+    pub fn anticone(&self, id: &HashValue) -> HashSet<HashValue> {
+        let mut anticone = HashSet::new();
+        if !self.contains(id) {
+            return anticone;
+        }
+        let mut visited = HashSet::new();
+        let mut stack: Vec<_> = self.tips.iter().copied().collect();
+        while let Some(b) = stack.pop() {
+            if !visited.insert(b) || b == *id || self.is_dag_ancestor_of(&b, id) {
+                continue;
+            }
+            if !self.is_dag_ancestor_of(id, &b) {
+                anticone.insert(b);
+            }
+            stack.extend(self.nodes[&b].parents.iter().copied());
+        }
+        anticone
+    }
+
+    pub fn tree_parent(&self, id: &HashValue) -> Option<HashValue> {
+        self.nodes.get(id)?.tree_parent
+    }
+
+    fn node_mut(&mut self, id: &HashValue) -> &mut IndexNode {
+        self.nodes.get_mut(id).expect("block is in the index")
+    }
+
+    // Carves an interval for a new tree child of `parent` out of its free slots.
+    // The selected-parent tree is mostly a chain, so a first child takes nearly
+    // all of the space and later siblings split what is left in half.
+    fn allocate(&mut self, parent: Option<HashValue>) -> Option<Interval> {
+        let (next, end, first) = match parent {
+            Some(p) => {
+                let node = &self.nodes[&p];
+                (node.next_free, node.interval.end, node.tree_children.is_empty())
+            }
+            None => (self.next_free, INTERVAL_END, self.roots.is_empty()),
+        };
+        if next > end {
+            return None;
+        }
+        let remaining = end - next + 1;
+        let size = if first {
+            remaining - remaining / 64
+        } else {
+            remaining / 2
+        };
+        if size == 0 {
+            return None;
+        }
+        let interval = Interval {
+            start: next,
+            end: next + size - 1,
+        };
+        match parent {
+            Some(p) => self.node_mut(&p).next_free = interval.end + 1,
+            None => self.next_free = interval.end + 1,
+        }
+        Some(interval)
+    }
+
+    // Reassigns every interval in proportion to subtree sizes. Relative order of
+    // intervals is preserved, so future covering sets stay sorted.
+    fn reindex(&mut self) {
+        let mut order = Vec::with_capacity(self.nodes.len());
+        let mut stack: Vec<_> = self.roots.iter().rev().copied().collect();
+        while let Some(id) = stack.pop() {
+            order.push(id);
+            stack.extend(self.nodes[&id].tree_children.iter().rev().copied());
+        }
+        let mut sizes = HashMap::with_capacity(order.len());
+        for id in order.iter().rev() {
+            let children: u64 = self.nodes[id].tree_children.iter().map(|c| sizes[c]).sum();
+            sizes.insert(*id, children + 1);
+        }
+
+        let roots = self.roots.clone();
+        self.next_free = self.assign(&roots, INTERVAL_START, INTERVAL_END, &sizes);
+        for id in &order {
+            let node = &self.nodes[id];
+            let (children, interval) = (node.tree_children.clone(), node.interval);
+            let next_free = self.assign(&children, interval.start + 1, interval.end, &sizes);
+            self.node_mut(id).next_free = next_free;
+        }
+    }
+
+    // Splits the slots from `next` to `end` among `children`. Each child gets one
+    // slot per block of its subtree, so its own children always fit, and the
+    // slots left over are shared in proportion to subtree sizes, holding back one
+    // share for children added later. Returns the first slot left unassigned.
+    fn assign(
+        &mut self,
+        children: &[HashValue],
+        mut next: u64,
+        end: u64,
+        sizes: &HashMap<HashValue, u64>,
+    ) -> u64 {
+        let needed: u64 = children.iter().map(|c| sizes[c]).sum();
+        // A block's interval holds its whole subtree, and the root interval far
+        // more blocks than fit in memory, so `needed` is always available.
+        let slack = (end + 1).saturating_sub(next).saturating_sub(needed) as u128;
+        for child in children {
+            let size = sizes[child] + (slack * sizes[child] as u128 / (needed + 1) as u128) as u64;
+            self.node_mut(child).interval = Interval {
+                start: next,
+                end: next + size - 1,
+            };
+            next += size;
+        }
+        next
+    }
+
+    // Moving a subtree changes the order of intervals, which covering sets are
+    // sorted by, and may nest an entry inside another, which then covers it.
+    fn normalize_future_covering_sets(&mut self) {
+        let intervals: HashMap<_, _> = self
+            .nodes
+            .iter()
+            .map(|(id, node)| (*id, node.interval))
+            .collect();
+        for node in self.nodes.values_mut() {
+            let fcs = &mut node.future_covering_set;
+            fcs.sort_by_key(|c| (intervals[c].start, Reverse(intervals[c].end)));
+            let mut covered_to = None;
+            fcs.retain(|c| {
+                let end = intervals[c].end;
+                if covered_to.is_some_and(|to| end <= to) {
+                    return false;
+                }
+                covered_to = Some(end);
+                true
+            });
+        }
+    }
+
+    // Makes every block in the closed past of `from` reach `target` and the rest of
+    // its future, which its covering set spans. A block that already reaches all
+    // of them is skipped along with its past, which reaches them through it.
+    fn cover(&mut self, from: Vec<HashValue>, target: HashValue) {
+        let mut covering = vec![target];
+        covering.extend(self.nodes[&target].future_covering_set.iter().copied());
+        let mut visited = HashSet::new();
+        let mut stack = from;
+        while let Some(m) = stack.pop() {
+            if !visited.insert(m) {
+                continue;
+            }
+            let unreached: Vec<_> = covering
+                .iter()
+                .copied()
+                .filter(|c| *c != m && !self.is_dag_ancestor_of(&m, c))
+                .collect();
+            if unreached.is_empty() {
+                continue;
+            }
+            for c in unreached {
+                self.insert_future_covering(&m, c);
+            }
+            stack.extend(self.nodes[&m].parents.iter().copied());
+        }
+    }
+
+    // Intervals are nested or disjoint, so entries starting inside the new one's
+    // interval are covered by it and make way.
+    fn insert_future_covering(&mut self, block: &HashValue, new_block: HashValue) {
+        let target = self.nodes[&new_block].interval;
+        let fcs = &self.nodes[block].future_covering_set;
+        let pos = fcs.partition_point(|c| self.nodes[c].interval.start <= target.start);
+        if pos > 0 && self.nodes[&fcs[pos - 1]].interval.contains(&target) {
+            return;
+        }
+        let from = fcs.partition_point(|c| self.nodes[c].interval.start < target.start);
+        let to = fcs.partition_point(|c| self.nodes[c].interval.start <= target.end);
+        self.node_mut(block)
+            .future_covering_set
+            .splice(from..to, [new_block]);
+    }
+}
+
+// Small deterministic LCG so the tests need no extra dependencies.
+#[cfg(test)]
+struct Lcg(u64);
+
+#[cfg(test)]
+impl Lcg {
+    fn next(&mut self, bound: usize) -> usize {
+        self.0 = self
+            .0
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        (self.0 >> 33) as usize % bound
+    }
+}
+
+// Parents of each block of a random DAG by position, selected parent first.
+// Every parent comes before its children.
+#[cfg(test)]
+fn random_dag(rng: &mut Lcg, count: usize) -> Vec<Vec<usize>> {
+    let mut dag = vec![vec![]];
+    for i in 1..count {
+        let mut ps: Vec<_> = (0..1 + rng.next(3))
+            .map(|_| i - 1 - rng.next(i.min(6)))
+            .collect();
+        ps.dedup();
+        let selected = rng.next(ps.len());
+        ps.swap(0, selected);
+        dag.push(ps);
+    }
+    dag
+}
+
+// Positions in the past of `d`, following only parents accepted by `keep`.
+#[cfg(test)]
+fn bfs_past(dag: &[Vec<usize>], d: usize, keep: impl Fn(usize) -> bool) -> HashSet<usize> {
+    let mut past = HashSet::new();
+    let mut stack = vec![d];
+    while let Some(i) = stack.pop() {
+        for p in dag[i].iter().copied().filter(|p| keep(*p)) {
+            if past.insert(p) {
+                stack.push(p);
+            }
+        }
+    }
+    past
+}
+
+#[test]
+fn test_index_matches_bfs_ancestry() {
+    let mut rng = Lcg(0x2545_f491_4f6c_dd1d);
+    // Long enough that the mostly-chain tree exhausts its slots and reindexes.
+    let count = 4_000;
+    let dag = random_dag(&mut rng, count);
+    let ids: Vec<_> = (0..count).map(|_| HashValue::random()).collect();
+    let mut index = ReachabilityIndex::new();
+    for (i, ps) in dag.iter().enumerate() {
+        let parents: Vec<_> = ps.iter().map(|p| ids[*p]).collect();
+        index.add_block(ids[i], &parents, parents.first().copied());
+    }
+    assert_eq!(index.len(), count);
+
+    for round in 0..200 {
+        let d = rng.next(count);
+        let past = bfs_past(&dag, d, |_| true);
+        for _ in 0..50 {
+            let a = rng.next(count);
+            assert_eq!(
+                index.is_dag_ancestor_of(&ids[a], &ids[d]),
+                past.contains(&a),
+                "ancestry of {} -> {}",
+                a,
+                d
+            );
+        }
+
+        // The walks agree with the BFS past and with each other.
+        if round % 10 == 0 {
+            let block_past: HashSet<_> = past.iter().map(|i| ids[*i]).collect();
+            assert_eq!(index.past(&ids[d]), block_past);
+            let future = index.future(&ids[d]);
+            let anticone = index.anticone(&ids[d]);
+            for (i, id) in ids.iter().enumerate() {
+                // Children come after their parents, so `future` already holds
+                // the verdict for every parent of `i`.
+                let in_future =
+                    i > d && dag[i].iter().any(|p| *p == d || future.contains(&ids[*p]));
+                assert_eq!(future.contains(id), in_future, "future of {} and {}", d, i);
+                assert_eq!(
+                    anticone.contains(id),
+                    i != d && !past.contains(&i) && !future.contains(id),
+                    "anticone of {} and {}",
+                    d,
+                    i
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn test_index_links_late_parents_and_evicts() {
+    let mut rng = Lcg(0x9e37_79b9_7f4a_7c15);
+    let count = 600;
+    let dag = random_dag(&mut rng, count);
+    let ids: Vec<_> = (0..count).map(|_| HashValue::random()).collect();
+    let add = |index: &mut ReachabilityIndex, i: usize| {
+        let parents: Vec<_> = dag[i].iter().map(|p| ids[*p]).collect();
+        index.add_block(ids[i], &parents, parents.first().copied());
+    };
+    let check = |index: &ReachabilityIndex, rng: &mut Lcg, indexed: &dyn Fn(usize) -> bool| {
+        for _ in 0..100 {
+            let d = std::iter::repeat_with(|| rng.next(count))
+                .find(|d| indexed(*d))
+                .unwrap();
+            let past = bfs_past(&dag, d, indexed);
+            let block_past: HashSet<_> = past.iter().map(|i| ids[*i]).collect();
+            assert_eq!(index.past(&ids[d]), block_past, "past of {}", d);
+            for a in (0..count).filter(|a| indexed(*a)).step_by(7) {
+                assert_eq!(
+                    index.is_dag_ancestor_of(&ids[a], &ids[d]),
+                    past.contains(&a),
+                    "ancestry of {} -> {}",
+                    a,
+                    d
+                );
+            }
+        }
+    };
+
+    // Every run of ten blocks arrives children first.
+    let mut index = ReachabilityIndex::new();
+    for chunk in (0..count).collect::<Vec<_>>().chunks(10) {
+        for i in chunk.iter().rev() {
+            add(&mut index, *i);
+        }
+    }
+    assert_eq!(index.len(), count);
+    check(&index, &mut rng, &|_| true);
+
+    // The oldest blocks hold their own past, so they can be evicted, and come
+    // back later to be linked again.
+    let evicted: HashSet<_> = ids[..300].iter().copied().collect();
+    index.remove(&evicted);
+    assert_eq!(index.len(), count - 300);
+    check(&index, &mut rng, &|i| i >= 300);
+    for i in (290..300).rev() {
+        add(&mut index, i);
+    }
+    check(&index, &mut rng, &|i| i >= 290);
+}
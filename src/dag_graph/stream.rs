@@ -0,0 +1,248 @@
+use crate::chain_reader::BlockWindow;
+use crate::dag_graph::{DagEdge, DagGraphBuilder, DagNode, NodeColor, VirtualBlock};
+use futures::stream::{self, Stream};
+use serde::Serialize;
+use starcoin_crypto::HashValue;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Selected chain blocks crawled per step of a streamed view.
+pub const SEGMENT_BLOCKS: u64 = 200;
+
+// Numbers kept below a segment's lowest mergeset member, since later mergesets
+// can reach a little further back and their parents further still.
+const MERGE_MARGIN: u64 = 100;
+
+/// One line of a streamed view.
+#[derive(Debug, Serialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum GraphItem {
+    /// A block of the view. A block first sent with an unknown color is sent
+    /// again once the chain block merging it has been crawled.
+    Node(DagNode),
+    /// An edge, sent after both of its nodes.
+    Edge(DagEdge),
+    Missing { id: HashValue },
+    /// Last line of a complete stream.
+    End {
+        truncated: bool,
+        tips: Vec<HashValue>,
+        virtual_block: Option<VirtualBlock>,
+    },
+}
+
+struct StreamState {
+    builder: DagGraphBuilder,
+    segment_blocks: u64,
+    // First chain number and block count still to crawl, once resolved.
+    range: Option<(u64, u64)>,
+    // Number and color of each node sent, down to `MERGE_MARGIN` below the lowest
+    // mergeset member of the last segment.
+    emitted: HashMap<HashValue, (u64, NodeColor)>,
+    // Missing blocks reported, with the lowest mergeset member of the segment
+    // that reported them, since their own number is unknown.
+    reported_missing: HashMap<HashValue, u64>,
+    truncated: bool,
+    queue: VecDeque<GraphItem>,
+    done: bool,
+}
+
+impl DagGraphBuilder {
+    /// Streams the view oldest first, crawling `segment_blocks` selected chain
+    /// blocks at a time so only about one segment is in memory. `CrawlLimits` apply to
+    /// each segment rather than to the whole view.
+    ///
+    /// Nodes carry no position since the layout needs the whole graph.
+    pub fn stream(&self, segment_blocks: u64) -> impl Stream<Item = anyhow::Result<GraphItem>> {
+        let state = StreamState {
+            builder: self.clone(),
+            segment_blocks: segment_blocks.max(1),
+            range: None,
+            emitted: HashMap::new(),
+            reported_missing: HashMap::new(),
+            truncated: false,
+            queue: VecDeque::new(),
+            done: false,
+        };
+        stream::try_unfold(state, |mut state| async move {
+            loop {
+                if let Some(item) = state.queue.pop_front() {
+                    return Ok(Some((item, state)));
+                }
+                if state.done {
+                    return Ok(None);
+                }
+                state.next_segment().await?;
+            }
+        })
+    }
+
+    // First chain number and block count of the window.
+    async fn chain_range(&self) -> anyhow::Result<(u64, u64)> {
+        match self.window().await? {
+            BlockWindow::Between { from, count } => Ok((from, count)),
+            BlockWindow::Latest(n) => Ok(match self.reader.selected_tip().await? {
+                Some(tip) => {
+                    let count = n.min(tip.number.saturating_add(1));
+                    (tip.number - count.saturating_sub(1), count)
+                }
+                None => (0, 0),
+            }),
+        }
+    }
+}
+
+impl StreamState {
+    async fn next_segment(&mut self) -> anyhow::Result<()> {
+        let (from, remaining) = match self.range {
+            Some(range) => range,
+            None => self.builder.chain_range().await?,
+        };
+        if remaining == 0 {
+            self.finish(Vec::new(), None);
+            return Ok(());
+        }
+        let count = remaining.min(self.segment_blocks);
+        let window = BlockWindow::Between { from, count };
+        let provider = self.builder.reader.dag_view(window, self.builder.limits).await?;
+        self.truncated |= provider.is_truncated();
+
+        let mut fresh = HashSet::new();
+        let mut nodes = Vec::new();
+        let view = provider.dag_nodes();
+        let lowest_merged = view
+            .iter()
+            .filter(|node| node.merged_by.is_some())
+            .map(|node| node.score)
+            .min();
+        for node in view {
+            let recolored = match self.emitted.get(&node.id) {
+                None => {
+                    fresh.insert(node.id);
+                    true
+                }
+                Some((_, color)) => {
+                    *color == NodeColor::Unknown && node.color != NodeColor::Unknown
+                }
+            };
+            if recolored {
+                self.emitted.insert(node.id, (node.score, node.color));
+                nodes.push(node);
+            }
+        }
+        self.builder.fill_bodies(&mut nodes).await?;
+        self.queue.extend(nodes.into_iter().map(GraphItem::Node));
+        for edge in provider.dag_edges() {
+            if fresh.contains(&edge.to) {
+                self.queue.push_back(GraphItem::Edge(edge));
+            }
+        }
+        // Parents outside this segment's view were usually sent with an older segment.
+        for mut edge in provider.dag_stub_edges() {
+            if !fresh.contains(&edge.to) {
+                continue;
+            }
+            if self.emitted.contains_key(&edge.from) {
+                edge.is_stub = false;
+            } else if !self.builder.stub_edges {
+                continue;
+            }
+            self.queue.push_back(GraphItem::Edge(edge));
+        }
+        for id in provider.missing_blocks() {
+            if let Entry::Vacant(entry) = self.reported_missing.entry(id) {
+                entry.insert(lowest_merged.unwrap_or(from));
+                self.queue.push_back(GraphItem::Missing { id });
+            }
+        }
+
+        // Blocks far enough below this segment's mergesets can no longer be merged
+        // by later segments; their children there link to them with stub edges.
+        if let Some(lowest) = lowest_merged {
+            let floor = lowest.saturating_sub(MERGE_MARGIN);
+            self.emitted.retain(|_, (number, _)| *number >= floor);
+            self.reported_missing.retain(|_, number| *number >= floor);
+        }
+        self.range = Some((from + count, remaining - count));
+        if remaining == count {
+            self.finish(provider.dag_tips(), provider.virtual_block());
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, tips: Vec<HashValue>, virtual_block: Option<VirtualBlock>) {
+        self.queue.push_back(GraphItem::End {
+            truncated: self.truncated,
+            tips,
+            virtual_block,
+        });
+        self.done = true;
+    }
+}
+
+#[tokio::test]
+async fn test_stream_sends_edges_after_their_nodes() -> anyhow::Result<()> {
+    use crate::chain_reader::mock::MockChainReader;
+    use futures::TryStreamExt;
+
+    let builder = MockChainReader::builder();
+    let items: Vec<GraphItem> = builder.stream(2).try_collect().await?;
+
+    let mut sent = HashSet::new();
+    for item in &items[..items.len() - 1] {
+        match item {
+            GraphItem::Node(node) => {
+                sent.insert(node.id);
+            }
+            GraphItem::Edge(edge) => {
+                assert!(sent.contains(&edge.from) && sent.contains(&edge.to));
+                assert!(!edge.is_stub);
+            }
+            GraphItem::Missing { .. } => {}
+            GraphItem::End { .. } => panic!("end before the last line"),
+        }
+    }
+    let Some(GraphItem::End { tips, .. }) = items.last() else {
+        panic!("stream does not end with an end line");
+    };
+    assert_eq!(tips.len(), 1);
+
+    let graph = builder.generate().await?;
+    for edge in graph.edges.iter().filter(|e| e.is_selected) {
+        assert!(sent.contains(&edge.to));
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_stream_keeps_blocks_merged_in_a_later_segment() -> anyhow::Result<()> {
+    use crate::chain_reader::mock::MockChainReader;
+    use futures::TryStreamExt;
+    use std::sync::Arc;
+
+    // With one chain block per segment, E merges S, whose parent A was sent with
+    // B's segment, three segments earlier.
+    let reader = MockChainReader::with_late_merge();
+    let builder = DagGraphBuilder::new(Arc::new(reader), BlockWindow::Latest(5));
+    let items: Vec<GraphItem> = builder.stream(1).try_collect().await?;
+
+    let mut sent = HashMap::new();
+    let mut edges = HashSet::new();
+    for item in &items {
+        match item {
+            GraphItem::Node(node) => {
+                let previous = sent.insert(node.id, node.color);
+                assert!(previous.is_none_or(|color| color == NodeColor::Unknown));
+            }
+            GraphItem::Edge(edge) => {
+                edges.insert((edge.from, edge.to));
+            }
+            _ => {}
+        }
+    }
+    let graph = builder.generate().await?;
+    for edge in graph.edges.iter().filter(|e| !e.is_stub) {
+        assert!(edges.contains(&(edge.from, edge.to)));
+    }
+    Ok(())
+}
@@ -0,0 +1,96 @@
+use crate::dag_graph::{DagGraphBuilder, DagNode, NodeColor};
+use serde::Serialize;
+use starcoin_crypto::HashValue;
+use std::collections::HashMap;
+
+/// A block of the view that includes the traced transaction.
+#[derive(Debug, Serialize, Clone)]
+pub struct Inclusion {
+    pub block: HashValue,
+    pub number: u64,
+    pub color: NodeColor,
+    pub merged_by: Option<HashValue>,
+    /// Set on the first inclusion in GHOSTDAG order, the one whose execution counts.
+    pub effective: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct TxnTrace {
+    pub transaction: HashValue,
+    /// Blocks including the transaction, in GHOSTDAG order.
+    pub inclusions: Vec<Inclusion>,
+    /// Set when the view was cut short, so inclusions may be missing.
+    pub truncated: bool,
+}
+
+impl DagGraphBuilder {
+    /// Finds every block of the view whose body includes `txn`. Fetches the body
+    /// of every block in the view.
+    pub async fn trace_transaction(&self, txn: HashValue) -> anyhow::Result<TxnTrace> {
+        let limits = self.limits.without_side_ghostdag();
+        let provider = self.reader.dag_view(self.window().await?, limits).await?;
+        let nodes = provider.dag_nodes();
+        let ids: Vec<_> = nodes.iter().map(|n| n.id).collect();
+        let bodies = self.reader.block_bodies(&ids).await?;
+
+        let rank = ghostdag_rank(&nodes);
+        let mut inclusions: Vec<_> = nodes
+            .iter()
+            .zip(bodies)
+            .filter(|(_, body)| body.as_ref().is_some_and(|b| b.transactions.contains(&txn)))
+            .map(|(node, _)| Inclusion {
+                block: node.id,
+                number: node.score,
+                color: node.color,
+                merged_by: node.merged_by,
+                effective: false,
+            })
+            .collect();
+        inclusions.sort_by_key(|i| rank[&i.block]);
+        if let Some(first) = inclusions.first_mut() {
+            first.effective = true;
+        }
+        Ok(TxnTrace {
+            transaction: txn,
+            inclusions,
+            truncated: provider.is_truncated(),
+        })
+    }
+}
+
+// Sort key of each node in GHOSTDAG order: by the chain block merging it, then by
+// its place in that block's mergeset. Blocks not merged within the view, such as
+// the selected tip, come last in node order.
+fn ghostdag_rank(nodes: &[DagNode]) -> HashMap<HashValue, (bool, usize, usize, usize)> {
+    let position: HashMap<_, _> = nodes.iter().enumerate().map(|(i, n)| (n.id, i)).collect();
+    nodes
+        .iter()
+        .enumerate()
+        .map(|(i, n)| {
+            let key = match n.merged_by.and_then(|m| position.get(&m)) {
+                Some(merger) => (false, *merger, n.mergeset_index.unwrap_or(0), i),
+                None => (true, 0, 0, i),
+            };
+            (n.id, key)
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_trace_parallel_inclusions() -> anyhow::Result<()> {
+    use crate::chain_reader::{mock::MockChainReader, BlockWindow};
+    use std::sync::Arc;
+
+    let reader = MockChainReader::new();
+    let txn = reader.shared_transaction();
+    let builder = DagGraphBuilder::new(Arc::new(reader), BlockWindow::Latest(4));
+
+    let trace = builder.trace_transaction(txn).await?;
+    let colors: Vec<_> = trace.inclusions.iter().map(|i| i.color).collect();
+    assert_eq!(colors, vec![NodeColor::Blue, NodeColor::Red]);
+    assert!(trace.inclusions[0].effective && !trace.inclusions[1].effective);
+
+    let trace = builder.trace_transaction(HashValue::random()).await?;
+    assert!(trace.inclusions.is_empty());
+    Ok(())
+}
@@ -0,0 +1,68 @@
+//! Wire encodings for API responses, chosen from the request's `Accept` header.
+
+use serde::Serialize;
+
+/// Response body encodings. The binary ones carry hashes as raw 32-byte strings
+/// instead of hex, since `HashValue` serializes compactly for non-human-readable
+/// formats.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    Json,
+    Cbor,
+    MessagePack,
+}
+
+impl Encoding {
+    /// The encoding for a media type such as `application/cbor`, if supported.
+    pub fn from_media_type(media_type: &str) -> Option<Self> {
+        match media_type {
+            "application/json" | "*/*" | "application/*" => Some(Encoding::Json),
+            "application/cbor" => Some(Encoding::Cbor),
+            "application/msgpack" | "application/x-msgpack" | "application/vnd.msgpack" => {
+                Some(Encoding::MessagePack)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Encoding::Json => "application/json",
+            Encoding::Cbor => "application/cbor",
+            Encoding::MessagePack => "application/msgpack",
+        }
+    }
+
+    pub fn encode<T: Serialize + ?Sized>(self, value: &T) -> anyhow::Result<Vec<u8>> {
+        Ok(match self {
+            Encoding::Json => serde_json::to_vec(value)?,
+            Encoding::Cbor => {
+                let mut buf = Vec::new();
+                ciborium::into_writer(value, &mut buf)?;
+                buf
+            }
+            Encoding::MessagePack => rmp_serde::to_vec_named(value)?,
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_binary_encodings_use_raw_hashes() -> anyhow::Result<()> {
+    use crate::chain_reader::mock::MockChainReader;
+
+    let builder = MockChainReader::builder();
+    let graph = builder.generate().await?;
+    let json = Encoding::Json.encode(&graph)?;
+
+    let cbor = Encoding::Cbor.encode(&graph)?;
+    let value: ciborium::Value = ciborium::from_reader(cbor.as_slice())?;
+    let nodes = value.as_map().unwrap().iter().find(|(k, _)| k.as_text() == Some("nodes"));
+    let node = &nodes.unwrap().1.as_array().unwrap()[0];
+    let id = node.as_map().unwrap().iter().find(|(k, _)| k.as_text() == Some("id"));
+    assert_eq!(id.unwrap().1.as_bytes().map(Vec::len), Some(32));
+    assert!(cbor.len() < json.len());
+
+    let msgpack = Encoding::MessagePack.encode(&graph)?;
+    assert!(msgpack.len() < json.len());
+    Ok(())
+}
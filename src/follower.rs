@@ -0,0 +1,245 @@
+//! Polls the head of the chain and reports what changed since the previous poll.
+
+use crate::chain_reader::ext::ChainBlock;
+use crate::chain_reader::{BlockWindow, ChainReader, CrawlLimits};
+use crate::dag_graph::{DagGraphProvider, DagStats, ReachabilityBlock};
+use async_trait::async_trait;
+use starcoin_crypto::HashValue;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Selected chain blocks followed unless a consumer asks for more.
+pub const DEFAULT_WINDOW: u64 = 20;
+
+/// Most selected chain blocks a poll crawls to cover the gap left since the
+/// last successful poll, in addition to its window.
+pub const MAX_BACKFILL: u64 = 1_000;
+
+/// The chain head as seen by one poll.
+#[derive(Debug, Clone)]
+pub struct Observation {
+    /// Latest selected chain blocks, oldest first.
+    pub chain: Vec<ChainBlock>,
+    /// Blocks without children, as reported by the node.
+    pub tip_count: usize,
+    /// Selected chain blocks seen by the previous poll that are no longer on the chain.
+    pub reorg_depth: u64,
+    /// Time since the follower last saw a new selected tip.
+    pub since_new_block: Duration,
+    /// Statistics over the crawled window.
+    pub stats: DagStats,
+    /// Blocks crawled by this poll, including any gap since the last
+    /// successful one, mostly parents first.
+    pub blocks: Vec<ReachabilityBlock>,
+}
+
+/// Told about every poll of a [`ChainFollower`].
+#[async_trait]
+pub trait FollowerSink: Send {
+    async fn observe(&mut self, obs: &Observation);
+
+    /// Called instead of `observe` when a poll fails.
+    async fn poll_failed(&mut self, _follower: &ChainFollower, _error: &anyhow::Error) {}
+}
+
+pub struct ChainFollower {
+    reader: Arc<dyn ChainReader>,
+    window: u64,
+    limits: CrawlLimits,
+    // Selected chain of the previous poll, by number.
+    chain: BTreeMap<u64, HashValue>,
+    tip: Option<HashValue>,
+    // Highest selected chain number crawled by a successful poll.
+    crawled: Option<u64>,
+    last_new_block: Instant,
+}
+
+impl ChainFollower {
+    /// Follows the latest `window` selected chain blocks.
+    pub fn new(reader: Arc<dyn ChainReader>, window: u64) -> Self {
+        Self {
+            reader,
+            window,
+            // Polls read the DAG's shape and the chain, never side blocks' blue work.
+            limits: CrawlLimits::default().without_side_ghostdag(),
+            chain: BTreeMap::new(),
+            tip: None,
+            crawled: None,
+            last_new_block: Instant::now(),
+        }
+    }
+
+    pub fn with_max_nodes(mut self, max_nodes: usize) -> Self {
+        self.limits.max_nodes = max_nodes;
+        self
+    }
+
+    /// Polls every `interval` forever, telling every sink about each poll.
+    pub async fn run(mut self, interval: Duration, mut sinks: Vec<Box<dyn FollowerSink>>) {
+        let mut ticker = tokio::time::interval(interval.max(Duration::from_secs(1)));
+        loop {
+            ticker.tick().await;
+            match self.poll().await {
+                Ok(obs) => {
+                    for sink in sinks.iter_mut() {
+                        sink.observe(&obs).await;
+                    }
+                }
+                Err(e) => {
+                    eprintln!("failed to poll the chain: {:#}", e);
+                    for sink in sinks.iter_mut() {
+                        sink.poll_failed(&self, &e).await;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Reads the node's tips, then crawls the window ending at their height, so
+    /// every part of the observation describes the same head. Chain blocks
+    /// between the previous poll's window and this one are crawled too, so
+    /// slow or failed polls leave no hole in `blocks`.
+    pub async fn poll(&mut self) -> anyhow::Result<Observation> {
+        let tips = self.reader.dag_tips().await?;
+        let head = tips.iter().map(|h| h.number).max();
+        let (chain, stats, blocks) = match head {
+            Some(head) => {
+                let count = self.window.min(head.saturating_add(1));
+                let from = head - count.saturating_sub(1);
+                let mut blocks = self.backfill(from).await?;
+                let window = BlockWindow::Between { from, count };
+                let view = self.reader.dag_view(window, self.limits).await?;
+                blocks.extend(view_blocks(&*view));
+                (view.chain_blocks(), view.dag_stats(), blocks)
+            }
+            None => (Vec::new(), DagStats::default(), Vec::new()),
+        };
+        let mut seen = HashSet::new();
+        let blocks = blocks.into_iter().filter(|b| seen.insert(b.id)).collect();
+        if head.is_some() {
+            self.crawled = head;
+        }
+
+        let reorg_depth = self.reorg_depth(&chain);
+        self.chain = chain.iter().map(|b| (b.number, b.id)).collect();
+        let tip = chain.last().map(|b| b.id);
+        if tip != self.tip {
+            self.tip = tip;
+            self.last_new_block = Instant::now();
+        }
+        Ok(Observation {
+            chain,
+            tip_count: tips.len(),
+            reorg_depth,
+            since_new_block: self.since_new_block(),
+            stats,
+            blocks,
+        })
+    }
+
+    // Blocks of the chain numbers after the last crawled one and before `from`,
+    // oldest first, one window at a time.
+    async fn backfill(&self, from: u64) -> anyhow::Result<Vec<ReachabilityBlock>> {
+        let Some(crawled) = self.crawled else {
+            return Ok(Vec::new());
+        };
+        let mut start = crawled
+            .saturating_add(1)
+            .max(from.saturating_sub(MAX_BACKFILL));
+        let mut blocks = Vec::new();
+        while start < from {
+            let count = self.window.min(from - start);
+            let window = BlockWindow::Between { from: start, count };
+            let view = self.reader.dag_view(window, self.limits).await?;
+            blocks.extend(view_blocks(&*view));
+            start += count;
+        }
+        Ok(blocks)
+    }
+
+    /// Time since a poll last saw a new selected tip.
+    pub fn since_new_block(&self) -> Duration {
+        self.last_new_block.elapsed()
+    }
+
+    /// Number of the selected tip seen by the last successful poll.
+    pub fn tip_number(&self) -> Option<u64> {
+        self.chain.keys().next_back().copied()
+    }
+
+    // Previously seen chain blocks, within the new chain's range or above it,
+    // whose number now holds another block or none.
+    fn reorg_depth(&self, chain: &[ChainBlock]) -> u64 {
+        let Some(first) = chain.first().map(|b| b.number) else {
+            return 0;
+        };
+        let current: HashMap<_, _> = chain.iter().map(|b| (b.number, b.id)).collect();
+        self.chain
+            .range(first..)
+            .filter(|(number, id)| current.get(number) != Some(id))
+            .count() as u64
+    }
+}
+
+// Every block of `view` in its canonical order, with the parents its edges name,
+// including those outside the view.
+fn view_blocks(view: &dyn DagGraphProvider) -> Vec<ReachabilityBlock> {
+    let mut blocks: Vec<_> = view
+        .dag_nodes()
+        .into_iter()
+        .map(|node| ReachabilityBlock {
+            id: node.id,
+            number: node.score,
+            parents: Vec::new(),
+            selected_parent: None,
+        })
+        .collect();
+    let position: HashMap<_, _> = blocks.iter().enumerate().map(|(i, b)| (b.id, i)).collect();
+    for edge in view.dag_edges().into_iter().chain(view.dag_stub_edges()) {
+        let block = &mut blocks[position[&edge.to]];
+        block.parents.push(edge.from);
+        if edge.is_selected {
+            block.selected_parent = Some(edge.from);
+        }
+    }
+    blocks
+}
+
+#[tokio::test]
+async fn test_follower_polls() -> anyhow::Result<()> {
+    use crate::chain_reader::mock::MockChainReader;
+
+    let mut follower = ChainFollower::new(Arc::new(MockChainReader::new()), 4);
+    let first = follower.poll().await?;
+    assert_eq!(first.chain.last().map(|b| b.number), Some(7));
+    assert_eq!(follower.tip_number(), Some(7));
+    assert_eq!((first.tip_count, first.reorg_depth), (2, 0));
+    assert_eq!(first.blocks.len(), first.stats.blocks);
+
+    // Pretend the previous poll saw another tip at the same height.
+    follower.chain.insert(7, HashValue::random());
+    let second = follower.poll().await?;
+    assert_eq!(second.reorg_depth, 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_follower_backfills_missed_blocks() -> anyhow::Result<()> {
+    use crate::chain_reader::mock::MockChainReader;
+
+    let reader = Arc::new(MockChainReader::new());
+    let plain = ChainFollower::new(reader.clone(), 2).poll().await?;
+
+    // The previous poll only got as far as number 1.
+    let mut follower = ChainFollower::new(reader, 2);
+    follower.crawled = Some(1);
+    let obs = follower.poll().await?;
+    let chain = |obs: &Observation| obs.chain.iter().map(|b| b.id).collect::<Vec<_>>();
+    assert_eq!(chain(&obs), chain(&plain));
+    assert!(obs.blocks.len() > plain.blocks.len());
+    let ids: HashSet<_> = obs.blocks.iter().map(|b| b.id).collect();
+    assert_eq!(ids.len(), obs.blocks.len());
+    assert!(plain.blocks.iter().all(|b| ids.contains(&b.id)));
+    Ok(())
+}
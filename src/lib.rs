@@ -1,5 +1,11 @@
+pub mod alerts;
 pub mod chain_reader;
 pub mod dag_graph;
+pub mod encoding;
+pub mod follower;
+pub mod metrics;
 pub mod prelude;
+pub mod render;
 pub mod server;
+pub mod snapshot;
 
@@ -1,7 +1,10 @@
 use anyhow::Result;
-use clap::Parser;
+use clap::{Parser, Subcommand};
+use starcoin_crypto::HashValue;
 use starcoin_graphd::prelude::*;
-use std::sync::Arc;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
 
 #[derive(Parser, Debug)]
 #[command(
@@ -16,12 +19,74 @@ struct Cli {
     listen: String,
 
     /// Network to connect to: halley, vega, or custom
-    #[arg(long, default_value = "halley", value_parser = ["halley", "vega", "custom"])]
+    #[arg(long, global = true, default_value = "halley", value_parser = ["halley", "vega", "custom"])]
     network: String,
 
     /// Custom RPC URL (only used if --network=custom)
-    #[arg(long)]
+    #[arg(long, global = true)]
     rpc_url: Option<String>,
+
+    /// Directory where saved snapshots are kept
+    #[arg(long, default_value = "snapshots")]
+    snapshot_dir: PathBuf,
+
+    /// Maximum number of snapshots kept; saving fails once reached
+    #[arg(long, default_value_t = DEFAULT_MAX_SNAPSHOTS)]
+    max_snapshots: usize,
+
+    /// JSON file of alert rules and webhooks to check while serving
+    #[arg(long)]
+    alerts: Option<PathBuf>,
+
+    /// Seconds between polls of the chain head for metrics and alerts
+    #[arg(long, default_value_t = 5)]
+    poll_interval: u64,
+
+    /// Maximum number of blocks fetched for a single DAG view
+    #[arg(long, global = true, default_value_t = 10_000)]
+    max_nodes: usize,
+
+    /// Number of block headers kept in the local cache
+    #[arg(long, global = true, default_value_t = DEFAULT_CACHE_CAPACITY)]
+    cache_capacity: usize,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Write the latest DAG view to a file instead of serving it
+    Export {
+        /// Output format: json, dot, graphml, gexf, mermaid, ascii or svg
+        #[arg(long, default_value = "json")]
+        format: Format,
+
+        /// Number of selected chain blocks in the view
+        #[arg(long, default_value_t = 20)]
+        window: u64,
+
+        /// Number of a past selected chain block to end the view at
+        #[arg(long)]
+        at: Option<u64>,
+
+        /// Fetch block bodies for transaction counts and gas
+        #[arg(long)]
+        bodies: bool,
+
+        /// Output file; stdout if omitted
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// List the blocks of the latest DAG view that include a transaction
+    Trace {
+        /// Transaction hash
+        txn: String,
+
+        /// Number of selected chain blocks searched
+        #[arg(long, default_value_t = 20)]
+        window: u64,
+    },
 }
 
 fn resolve_rpc_url(cli: &Cli) -> Result<String> {
@@ -36,12 +101,65 @@ fn resolve_rpc_url(cli: &Cli) -> Result<String> {
     }
 }
 
-
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
     let rpc_url = resolve_rpc_url(&cli)?;
-    let reader: Arc<dyn ChainReader> = Arc::new(RpcChainReader::new(&rpc_url));
-    let builder = DagGraphBuilder::new(reader, BlockWindow::Latest(20));
-    start_server(builder, &cli.listen).await
+    let metrics = Arc::new(Metrics::new());
+    let reader: Arc<dyn ChainReader> = Arc::new(
+        CachedChainReader::with_capacity(
+            RpcChainReader::new(&rpc_url).with_metrics(metrics.clone()),
+            cli.cache_capacity,
+        )
+        .with_metrics(metrics.clone()),
+    );
+    match &cli.command {
+        None => {
+            let reachability = Arc::new(RwLock::new(Reachability::default()));
+            let mut sinks: Vec<Box<dyn FollowerSink>> =
+                vec![Box::new(metrics.clone()), Box::new(reachability.clone())];
+            let mut window = DEFAULT_FOLLOW_WINDOW;
+            if let Some(path) = &cli.alerts {
+                let config = AlertConfig::from_file(path)?;
+                window = config.window();
+                sinks.push(Box::new(AlertEngine::new(config)));
+            }
+            let follower = ChainFollower::new(reader.clone(), window).with_max_nodes(cli.max_nodes);
+            tokio::spawn(follower.run(Duration::from_secs(cli.poll_interval), sinks));
+            let builder = DagGraphBuilder::new(reader, BlockWindow::Latest(20))
+                .with_max_nodes(cli.max_nodes);
+            let snapshots = SnapshotStore::new(&cli.snapshot_dir).with_max_snapshots(cli.max_snapshots);
+            start_server(builder, snapshots, metrics, reachability, &cli.listen).await
+        }
+        Some(Command::Export {
+            format,
+            window,
+            at,
+            bodies,
+            output,
+        }) => {
+            let mut builder = DagGraphBuilder::new(reader, BlockWindow::Latest(*window))
+                .with_max_nodes(cli.max_nodes)
+                .with_bodies(*bodies);
+            if let Some(number) = at {
+                builder = builder.with_tip(*number);
+            }
+            let graph = builder.generate().await?;
+            let body = format.render(&graph)?;
+            match output {
+                Some(path) => std::fs::write(path, body)?,
+                None => print!("{}", body),
+            }
+            Ok(())
+        }
+        Some(Command::Trace { txn, window }) => {
+            let txn = HashValue::from_hex_literal(txn)?;
+            let trace = DagGraphBuilder::new(reader, BlockWindow::Latest(*window))
+                .with_max_nodes(cli.max_nodes)
+                .trace_transaction(txn)
+                .await?;
+            println!("{}", serde_json::to_string_pretty(&trace)?);
+            Ok(())
+        }
+    }
 }
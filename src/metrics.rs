@@ -0,0 +1,198 @@
+//! Prometheus metrics for the service itself and for the chain it watches.
+
+use crate::follower::{ChainFollower, FollowerSink, Observation};
+use async_trait::async_trait;
+use prometheus_client::encoding::text::encode;
+use prometheus_client::encoding::EncodeLabelSet;
+use prometheus_client::metrics::counter::Counter;
+use prometheus_client::metrics::family::Family;
+use prometheus_client::metrics::gauge::Gauge;
+use prometheus_client::metrics::histogram::{exponential_buckets, Histogram};
+use prometheus_client::registry::Registry;
+use std::sync::atomic::AtomicU64;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct RouteLabels {
+    route: String,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct MethodLabels {
+    method: String,
+}
+
+// 5ms up to about 20s.
+fn latency_histogram() -> Histogram {
+    Histogram::new(exponential_buckets(0.005, 2.0, 12))
+}
+
+/// Every metric graphd exports. Shared behind an `Arc` by the server, the RPC
+/// reader, the header cache and the chain follower.
+pub struct Metrics {
+    registry: Registry,
+    request_duration: Family<RouteLabels, Histogram>,
+    rpc_duration: Family<MethodLabels, Histogram>,
+    rpc_errors: Family<MethodLabels, Counter>,
+    cache_hits: Counter,
+    cache_misses: Counter,
+    tip_height: Gauge,
+    tip_blue_score: Gauge,
+    tip_count: Gauge,
+    red_ratio: Gauge<f64, AtomicU64>,
+    chain_poll_errors: Counter,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let request_duration =
+            Family::<RouteLabels, Histogram>::new_with_constructor(latency_histogram as fn() -> _);
+        let rpc_duration =
+            Family::<MethodLabels, Histogram>::new_with_constructor(latency_histogram as fn() -> _);
+        let rpc_errors = Family::<MethodLabels, Counter>::default();
+        let cache_hits = Counter::default();
+        let cache_misses = Counter::default();
+        let tip_height = Gauge::default();
+        let tip_blue_score = Gauge::default();
+        let tip_count = Gauge::default();
+        let red_ratio = Gauge::<f64, AtomicU64>::default();
+        let chain_poll_errors = Counter::default();
+
+        let mut registry = Registry::with_prefix("graphd");
+        registry.register(
+            "request_duration_seconds",
+            "HTTP request latency by route",
+            request_duration.clone(),
+        );
+        registry.register(
+            "rpc_duration_seconds",
+            "Upstream RPC call latency by method",
+            rpc_duration.clone(),
+        );
+        registry.register("rpc_errors", "Failed upstream RPC calls by method", rpc_errors.clone());
+        registry.register("cache_hits", "Headers served from the local cache", cache_hits.clone());
+        registry.register("cache_misses", "Headers fetched upstream on a cache miss", cache_misses.clone());
+        registry.register("tip_height", "Highest block number at the last poll", tip_height.clone());
+        registry.register("tip_blue_score", "Highest blue score at the last poll", tip_blue_score.clone());
+        registry.register("tip_count", "Blocks without children at the last poll", tip_count.clone());
+        registry.register("red_ratio", "Share of red blocks in the followed mergesets", red_ratio.clone());
+        registry.register(
+            "chain_poll_errors",
+            "Polls of the chain head that failed",
+            chain_poll_errors.clone(),
+        );
+
+        Self {
+            registry,
+            request_duration,
+            rpc_duration,
+            rpc_errors,
+            cache_hits,
+            cache_misses,
+            tip_height,
+            tip_blue_score,
+            tip_count,
+            red_ratio,
+            chain_poll_errors,
+        }
+    }
+
+    pub fn observe_request(&self, route: &str, elapsed: Duration) {
+        let labels = RouteLabels {
+            route: route.to_string(),
+        };
+        self.request_duration
+            .get_or_create(&labels)
+            .observe(elapsed.as_secs_f64());
+    }
+
+    pub fn observe_rpc(&self, method: &str, elapsed: Duration, ok: bool) {
+        let labels = MethodLabels {
+            method: method.to_string(),
+        };
+        self.rpc_duration
+            .get_or_create(&labels)
+            .observe(elapsed.as_secs_f64());
+        if !ok {
+            self.rpc_errors.get_or_create(&labels).inc();
+        }
+    }
+
+    pub fn record_cache_lookup(&self, hits: usize, misses: usize) {
+        self.cache_hits.inc_by(hits as u64);
+        self.cache_misses.inc_by(misses as u64);
+    }
+
+    /// Updates the chain gauges from a poll of the chain head.
+    pub fn observe_chain(&self, obs: &Observation) {
+        self.tip_height.set(obs.stats.tip_number.unwrap_or(0) as i64);
+        self.tip_blue_score.set(obs.stats.tip_blue_score.unwrap_or(0) as i64);
+        self.tip_count.set(obs.tip_count as i64);
+        self.red_ratio.set(obs.stats.red_ratio.unwrap_or(0.0));
+    }
+
+    /// All metrics in the OpenMetrics text format.
+    pub fn encode(&self) -> anyhow::Result<String> {
+        let mut out = String::new();
+        encode(&mut out, &self.registry)?;
+        Ok(out)
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Keeps the chain gauges current, so scrapes never reach the node.
+#[async_trait]
+impl FollowerSink for Arc<Metrics> {
+    async fn observe(&mut self, obs: &Observation) {
+        self.observe_chain(obs);
+    }
+
+    async fn poll_failed(&mut self, _follower: &ChainFollower, _error: &anyhow::Error) {
+        self.chain_poll_errors.inc();
+    }
+}
+
+#[tokio::test]
+async fn test_encode_metrics() -> anyhow::Result<()> {
+    use crate::chain_reader::mock::MockChainReader;
+    use crate::dag_graph::DagStats;
+
+    let mut metrics = Arc::new(Metrics::new());
+    metrics.observe_request("/dag_view", Duration::from_millis(12));
+    metrics.observe_rpc("chain.get_headers", Duration::from_millis(3), false);
+    metrics.record_cache_lookup(3, 1);
+    metrics
+        .observe(&Observation {
+            chain: Vec::new(),
+            tip_count: 2,
+            reorg_depth: 0,
+            since_new_block: Duration::ZERO,
+            stats: DagStats {
+                tip_number: Some(7),
+                red_ratio: Some(0.25),
+                ..Default::default()
+            },
+            blocks: Vec::new(),
+        })
+        .await;
+    let follower = ChainFollower::new(Arc::new(MockChainReader::new()), 4);
+    metrics
+        .poll_failed(&follower, &anyhow::anyhow!("timed out"))
+        .await;
+
+    let text = metrics.encode()?;
+    assert!(text.contains("graphd_request_duration_seconds_count{route=\"/dag_view\"} 1"));
+    assert!(text.contains("graphd_rpc_errors_total{method=\"chain.get_headers\"} 1"));
+    assert!(text.contains("graphd_cache_hits_total 3"));
+    assert!(text.contains("graphd_tip_height 7"));
+    assert!(text.contains("graphd_tip_count 2"));
+    assert!(text.contains("graphd_chain_poll_errors_total 1"));
+    assert!(text.contains("graphd_red_ratio 0.25"));
+    Ok(())
+}
@@ -1,6 +1,14 @@
+pub use crate::alerts::{AlertConfig, AlertEngine};
+pub use crate::chain_reader::cache::{CachedChainReader, DEFAULT_CAPACITY as DEFAULT_CACHE_CAPACITY};
 pub use crate::chain_reader::ext::ChainReaderExt;
 pub use crate::chain_reader::rpc::RpcChainReader;
 pub use crate::chain_reader::ChainReader;
+pub use crate::chain_reader::BlockQuery;
 pub use crate::chain_reader::BlockWindow;
-pub use crate::dag_graph::DagGraphBuilder;
+pub use crate::chain_reader::CrawlLimits;
+pub use crate::dag_graph::{DagGraphBuilder, Reachability};
+pub use crate::follower::{ChainFollower, FollowerSink, DEFAULT_WINDOW as DEFAULT_FOLLOW_WINDOW};
+pub use crate::metrics::Metrics;
+pub use crate::render::Format;
 pub use crate::server::start_server;
+pub use crate::snapshot::{SnapshotStore, DEFAULT_MAX_SNAPSHOTS};
@@ -0,0 +1,78 @@
+use crate::dag_graph::{DagGraph, NodeColor};
+use crate::render::short_hash;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+/// Renders `graph` as plain text, one row per block height, oldest first.
+///
+/// Each block is shown as `[B]`, `[R]` or `[?]` for blue, red and unknown, followed
+/// by its short hash and its parents; the selected parent is marked with `*` and
+/// parents outside the view with `~`.
+pub fn to_ascii(graph: &DagGraph) -> String {
+    let mut parents: HashMap<_, Vec<_>> = HashMap::new();
+    for edge in &graph.edges {
+        parents.entry(edge.to).or_default().push(edge);
+    }
+    let mut heights: BTreeMap<u64, Vec<_>> = BTreeMap::new();
+    for node in &graph.nodes {
+        heights.entry(node.score).or_default().push(node);
+    }
+
+    let width = heights
+        .keys()
+        .last()
+        .map_or(1, |h| h.to_string().len());
+    let mut out = String::new();
+    for (height, mut nodes) in heights {
+        nodes.sort_by_key(|n| n.id);
+        for (i, node) in nodes.into_iter().enumerate() {
+            let label = if i == 0 {
+                height.to_string()
+            } else {
+                String::new()
+            };
+            let marker = match node.color {
+                NodeColor::Blue => 'B',
+                NodeColor::Red => 'R',
+                NodeColor::Unknown => '?',
+            };
+            let _ = write!(out, "{:>width$} | [{}] {}", label, marker, short_hash(&node.id));
+            let mut edges = parents.remove(&node.id).unwrap_or_default();
+            edges.sort_by_key(|e| e.from);
+            if !edges.is_empty() || node.is_boundary {
+                out.push_str(" <-");
+            }
+            for edge in edges {
+                let mark = if edge.is_selected { "*" } else if edge.is_stub { "~" } else { "" };
+                let _ = write!(out, " {}{}", mark, short_hash(&edge.from));
+            }
+            if node.is_boundary {
+                let _ = write!(out, " (+{} outside view)", node.omitted_parents);
+            }
+            out.push('\n');
+        }
+    }
+    out
+}
+
+#[tokio::test]
+async fn test_text_renderings() -> anyhow::Result<()> {
+    use crate::chain_reader::mock::MockChainReader;
+    use crate::render::to_mermaid;
+
+    let builder = MockChainReader::builder();
+    let graph = builder.generate().await?;
+
+    let ascii = to_ascii(&graph);
+    assert_eq!(ascii.lines().count(), graph.nodes.len());
+    assert!(ascii.starts_with("0 | [B] "));
+    assert_eq!(ascii.matches("[R]").count(), 1);
+    let selected = graph.edges.iter().filter(|e| e.is_selected).count();
+    assert_eq!(ascii.matches(" *").count(), selected);
+
+    let mermaid = to_mermaid(&graph);
+    assert!(mermaid.starts_with("flowchart LR\n"));
+    assert_eq!(mermaid.matches(":::red").count(), 1);
+    assert_eq!(mermaid.matches(" ==> ").count(), selected);
+    Ok(())
+}
@@ -0,0 +1,79 @@
+use crate::dag_graph::DagGraph;
+use crate::render::{fill_color, short_hash};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// Renders `graph` in Graphviz DOT, flowing from parents on the left to children
+/// on the right.
+///
+/// Nodes are filled by color, blocks sharing a blue score are placed in the same
+/// rank, selected-parent edges are bold and stub edges dashed.
+pub fn to_dot(graph: &DagGraph) -> String {
+    let mut out = String::new();
+    out.push_str("digraph dag {\n");
+    out.push_str("  rankdir=LR;\n");
+    out.push_str("  node [shape=box, style=filled, fontname=\"monospace\"];\n");
+
+    let mut ranks: BTreeMap<u64, Vec<String>> = BTreeMap::new();
+    for node in &graph.nodes {
+        let id = node.id.to_hex_literal();
+        let style = if node.is_boundary {
+            "filled,dashed"
+        } else {
+            "filled"
+        };
+        let _ = writeln!(
+            out,
+            "  \"{}\" [label=\"#{}\\n{}\", fillcolor=\"{}\", style=\"{}\"];",
+            id,
+            node.score,
+            short_hash(&node.id),
+            fill_color(node.color),
+            style
+        );
+        if let Some(blue_score) = node.blue_score {
+            ranks.entry(blue_score).or_default().push(id);
+        }
+    }
+
+    for ids in ranks.values().filter(|ids| ids.len() > 1) {
+        let members: Vec<_> = ids.iter().map(|id| format!("\"{}\";", id)).collect();
+        let _ = writeln!(out, "  {{ rank=same; {} }}", members.join(" "));
+    }
+
+    for edge in &graph.edges {
+        let attrs = match (edge.is_selected, edge.is_stub) {
+            (_, true) => " [style=dashed, color=gray]",
+            (true, false) => " [style=bold, penwidth=2]",
+            (false, false) => "",
+        };
+        let _ = writeln!(
+            out,
+            "  \"{}\" -> \"{}\"{};",
+            edge.from.to_hex_literal(),
+            edge.to.to_hex_literal(),
+            attrs
+        );
+    }
+
+    out.push_str("}\n");
+    out
+}
+
+#[tokio::test]
+async fn test_to_dot() -> anyhow::Result<()> {
+    use crate::chain_reader::mock::MockChainReader;
+
+    let builder = MockChainReader::builder();
+    let graph = builder.generate().await?;
+    let dot = to_dot(&graph);
+
+    assert!(dot.starts_with("digraph dag {"));
+    assert_eq!(dot.matches(" [label=").count(), graph.nodes.len());
+    assert_eq!(dot.matches(" -> ").count(), graph.edges.len());
+    let selected = graph.edges.iter().filter(|e| e.is_selected).count();
+    assert_eq!(dot.matches("style=bold").count(), selected);
+    // F and G share blue score 5.
+    assert_eq!(dot.matches("rank=same").count(), 1);
+    Ok(())
+}
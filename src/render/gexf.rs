@@ -0,0 +1,86 @@
+use crate::dag_graph::DagGraph;
+use crate::render::{color_name, short_hash};
+use std::fmt::Write;
+
+const NODE_ATTRIBUTES: [(&str, &str); 6] = [
+    ("number", "long"),
+    ("blue_score", "long"),
+    ("color", "string"),
+    ("timestamp", "long"),
+    ("merged_by", "string"),
+    ("is_boundary", "boolean"),
+];
+const EDGE_ATTRIBUTES: [(&str, &str); 2] = [("is_selected", "boolean"), ("is_stub", "boolean")];
+
+/// Renders `graph` as GEXF 1.3 for Gephi. Attributes are declared by name and
+/// referenced by their position in the declaration list.
+pub fn to_gexf(graph: &DagGraph) -> String {
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str("<gexf xmlns=\"http://gexf.net/1.3\" version=\"1.3\">\n");
+    out.push_str("  <graph defaultedgetype=\"directed\">\n");
+    for (class, attributes) in [("node", &NODE_ATTRIBUTES[..]), ("edge", &EDGE_ATTRIBUTES[..])] {
+        let _ = writeln!(out, "    <attributes class=\"{class}\">");
+        for (i, (title, ty)) in attributes.iter().enumerate() {
+            let _ = writeln!(
+                out,
+                "      <attribute id=\"{i}\" title=\"{title}\" type=\"{ty}\"/>"
+            );
+        }
+        out.push_str("    </attributes>\n");
+    }
+
+    out.push_str("    <nodes>\n");
+    for node in &graph.nodes {
+        let _ = writeln!(
+            out,
+            "      <node id=\"{}\" label=\"#{} {}\">",
+            node.id.to_hex_literal(),
+            node.score,
+            short_hash(&node.id)
+        );
+        let values = [
+            Some(node.score.to_string()),
+            node.blue_score.map(|s| s.to_string()),
+            Some(color_name(node.color).to_string()),
+            Some(node.timestamp.to_string()),
+            node.merged_by.map(|id| id.to_hex_literal()),
+            Some(node.is_boundary.to_string()),
+        ];
+        write_attvalues(&mut out, &values);
+        out.push_str("      </node>\n");
+    }
+    out.push_str("    </nodes>\n");
+
+    out.push_str("    <edges>\n");
+    for (i, edge) in graph.edges.iter().enumerate() {
+        let _ = writeln!(
+            out,
+            "      <edge id=\"{}\" source=\"{}\" target=\"{}\">",
+            i,
+            edge.from.to_hex_literal(),
+            edge.to.to_hex_literal()
+        );
+        let values = [
+            Some(edge.is_selected.to_string()),
+            Some(edge.is_stub.to_string()),
+        ];
+        write_attvalues(&mut out, &values);
+        out.push_str("      </edge>\n");
+    }
+    out.push_str("    </edges>\n");
+
+    out.push_str("  </graph>\n</gexf>\n");
+    out
+}
+
+// Values are indexed like the attribute declarations; `None` is left out.
+fn write_attvalues(out: &mut String, values: &[Option<String>]) {
+    out.push_str("        <attvalues>\n");
+    for (i, value) in values.iter().enumerate() {
+        if let Some(value) = value {
+            let _ = writeln!(out, "          <attvalue for=\"{i}\" value=\"{value}\"/>");
+        }
+    }
+    out.push_str("        </attvalues>\n");
+}
@@ -0,0 +1,71 @@
+use crate::dag_graph::DagGraph;
+use crate::render::color_name;
+use std::fmt::Write;
+
+const NODE_KEYS: [(&str, &str); 8] = [
+    ("number", "long"),
+    ("blue_score", "long"),
+    ("color", "string"),
+    ("timestamp", "long"),
+    ("merged_by", "string"),
+    ("is_boundary", "boolean"),
+    ("txn_count", "long"),
+    ("gas_used", "long"),
+];
+const EDGE_KEYS: [(&str, &str); 2] = [("is_selected", "boolean"), ("is_stub", "boolean")];
+
+/// Renders `graph` as GraphML, readable by networkx, Gephi and yEd.
+/// Optional attributes such as a missing blue score are left out of the node.
+pub fn to_graphml(graph: &DagGraph) -> String {
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str("<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n");
+    for (target, keys) in [("node", &NODE_KEYS[..]), ("edge", &EDGE_KEYS[..])] {
+        for (name, ty) in keys {
+            let _ = writeln!(
+                out,
+                "  <key id=\"{name}\" for=\"{target}\" attr.name=\"{name}\" attr.type=\"{ty}\"/>"
+            );
+        }
+    }
+    out.push_str("  <graph id=\"dag\" edgedefault=\"directed\">\n");
+
+    for node in &graph.nodes {
+        let _ = writeln!(out, "    <node id=\"{}\">", node.id.to_hex_literal());
+        let mut data = vec![("number", node.score.to_string())];
+        if let Some(blue_score) = node.blue_score {
+            data.push(("blue_score", blue_score.to_string()));
+        }
+        data.push(("color", color_name(node.color).to_string()));
+        data.push(("timestamp", node.timestamp.to_string()));
+        if let Some(merged_by) = node.merged_by {
+            data.push(("merged_by", merged_by.to_hex_literal()));
+        }
+        data.push(("is_boundary", node.is_boundary.to_string()));
+        if let Some(txn_count) = node.txn_count {
+            data.push(("txn_count", txn_count.to_string()));
+        }
+        if let Some(gas_used) = node.gas_used {
+            data.push(("gas_used", gas_used.to_string()));
+        }
+        for (key, value) in data {
+            let _ = writeln!(out, "      <data key=\"{key}\">{value}</data>");
+        }
+        out.push_str("    </node>\n");
+    }
+
+    for edge in &graph.edges {
+        let _ = writeln!(
+            out,
+            "    <edge source=\"{}\" target=\"{}\">",
+            edge.from.to_hex_literal(),
+            edge.to.to_hex_literal()
+        );
+        let _ = writeln!(out, "      <data key=\"is_selected\">{}</data>", edge.is_selected);
+        let _ = writeln!(out, "      <data key=\"is_stub\">{}</data>", edge.is_stub);
+        out.push_str("    </edge>\n");
+    }
+
+    out.push_str("  </graph>\n</graphml>\n");
+    out
+}
@@ -0,0 +1,52 @@
+use crate::dag_graph::{DagGraph, NodeColor};
+use crate::render::{color_name, fill_color, short_hash};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// Renders `graph` as a Mermaid flowchart for markdown tickets and chat.
+///
+/// Mermaid ids are positional (`n0`, `n1`, ...) and labels carry the number and
+/// short hash. Selected-parent edges are thick, stub edges dotted and point at
+/// plain placeholder nodes.
+pub fn to_mermaid(graph: &DagGraph) -> String {
+    let mut out = String::from("flowchart LR\n");
+    let mut ids = HashMap::new();
+    for (i, node) in graph.nodes.iter().enumerate() {
+        let id = format!("n{}", i);
+        let _ = writeln!(
+            out,
+            "  {}[\"#{} {}\"]:::{}",
+            id,
+            node.score,
+            short_hash(&node.id),
+            color_name(node.color)
+        );
+        ids.insert(node.id, id);
+    }
+
+    for edge in &graph.edges {
+        let next = ids.len();
+        let from = ids.entry(edge.from).or_insert_with(|| {
+            let id = format!("n{}", next);
+            let _ = writeln!(out, "  {}[\"{}\"]", id, short_hash(&edge.from));
+            id
+        });
+        let from = from.clone();
+        let arrow = match (edge.is_selected, edge.is_stub) {
+            (_, true) => "-.->",
+            (true, false) => "==>",
+            (false, false) => "-->",
+        };
+        let _ = writeln!(out, "  {} {} {}", from, arrow, ids[&edge.to]);
+    }
+
+    for color in [NodeColor::Blue, NodeColor::Red, NodeColor::Unknown] {
+        let _ = writeln!(
+            out,
+            "  classDef {} fill:{}",
+            color_name(color),
+            fill_color(color)
+        );
+    }
+    out
+}
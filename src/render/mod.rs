@@ -0,0 +1,115 @@
+//! Renderings of a [`DagGraph`] for tools outside the web UI.
+
+use crate::dag_graph::{DagGraph, NodeColor};
+use starcoin_crypto::HashValue;
+use std::str::FromStr;
+
+pub mod ascii;
+pub mod dot;
+pub mod gexf;
+pub mod graphml;
+pub mod mermaid;
+pub mod svg;
+
+pub use ascii::to_ascii;
+pub use dot::to_dot;
+pub use gexf::to_gexf;
+pub use graphml::to_graphml;
+pub use mermaid::to_mermaid;
+pub use svg::to_svg;
+
+/// Output formats a `DagGraph` can be exported to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Dot,
+    Graphml,
+    Gexf,
+    Mermaid,
+    Ascii,
+    Svg,
+}
+
+impl Format {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Format::Json => "application/json",
+            Format::Dot => "text/vnd.graphviz; charset=utf-8",
+            Format::Graphml => "application/graphml+xml",
+            Format::Gexf => "application/gexf+xml",
+            Format::Mermaid => "text/vnd.mermaid; charset=utf-8",
+            Format::Ascii => "text/plain; charset=utf-8",
+            Format::Svg => "image/svg+xml",
+        }
+    }
+
+    pub fn render(self, graph: &DagGraph) -> anyhow::Result<String> {
+        Ok(match self {
+            Format::Json => serde_json::to_string_pretty(graph)?,
+            Format::Dot => to_dot(graph),
+            Format::Graphml => to_graphml(graph),
+            Format::Gexf => to_gexf(graph),
+            Format::Mermaid => to_mermaid(graph),
+            Format::Ascii => to_ascii(graph),
+            Format::Svg => to_svg(graph),
+        })
+    }
+}
+
+impl FromStr for Format {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "json" => Ok(Format::Json),
+            "dot" => Ok(Format::Dot),
+            "graphml" => Ok(Format::Graphml),
+            "gexf" => Ok(Format::Gexf),
+            "mermaid" | "mmd" => Ok(Format::Mermaid),
+            "ascii" | "txt" => Ok(Format::Ascii),
+            "svg" => Ok(Format::Svg),
+            other => Err(anyhow::anyhow!("Unsupported format: {}", other)),
+        }
+    }
+}
+
+// Leading hex digits of a hash, enough to tell blocks apart in a picture.
+pub(crate) fn short_hash(id: &HashValue) -> String {
+    id.to_hex()[..8].to_string()
+}
+
+pub(crate) fn fill_color(color: NodeColor) -> &'static str {
+    match color {
+        NodeColor::Blue => "#4a90e2",
+        NodeColor::Red => "#e94b3c",
+        NodeColor::Unknown => "#c8c8c8",
+    }
+}
+
+// Same spelling as the JSON encoding of `NodeColor`.
+pub(crate) fn color_name(color: NodeColor) -> &'static str {
+    match color {
+        NodeColor::Blue => "blue",
+        NodeColor::Red => "red",
+        NodeColor::Unknown => "unknown",
+    }
+}
+
+#[tokio::test]
+async fn test_xml_exports() -> anyhow::Result<()> {
+    use crate::chain_reader::mock::MockChainReader;
+
+    let builder = MockChainReader::builder();
+    let graph = builder.generate().await?;
+
+    let graphml = Format::Graphml.render(&graph)?;
+    assert_eq!(graphml.matches("<node id=").count(), graph.nodes.len());
+    assert_eq!(graphml.matches("<edge source=").count(), graph.edges.len());
+    assert_eq!(graphml.matches("<data key=\"color\">red</data>").count(), 1);
+
+    let gexf = Format::Gexf.render(&graph)?;
+    assert_eq!(gexf.matches("<node id=").count(), graph.nodes.len());
+    assert_eq!(gexf.matches("<edge id=").count(), graph.edges.len());
+    assert_eq!(gexf.matches("value=\"red\"").count(), 1);
+    Ok(())
+}
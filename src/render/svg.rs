@@ -0,0 +1,93 @@
+use crate::dag_graph::layout::{Layout, Position};
+use crate::dag_graph::DagGraph;
+use crate::render::{fill_color, short_hash};
+use std::fmt::Write;
+
+const NODE_WIDTH: f64 = 96.0;
+const NODE_HEIGHT: f64 = 30.0;
+
+/// Renders `graph` as a standalone SVG image at the positions of the server-side layout.
+///
+/// Selected-parent edges are drawn thicker; stub edges are left out since their
+/// parents have no position.
+pub fn to_svg(graph: &DagGraph) -> String {
+    let layout = Layout::of(graph);
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"{x} 0 {w} {h}\">",
+        x = layout.left,
+        w = layout.width,
+        h = layout.height
+    );
+    let _ = writeln!(
+        out,
+        "  <rect x=\"{}\" width=\"100%\" height=\"100%\" fill=\"#ffffff\"/>",
+        layout.left
+    );
+
+    out.push_str("  <g stroke=\"#777777\" fill=\"none\">\n");
+    for edge in &graph.edges {
+        let (Some(from), Some(to)) = (
+            layout.positions.get(&edge.from),
+            layout.positions.get(&edge.to),
+        ) else {
+            continue;
+        };
+        let stroke = if edge.is_selected { 2.5 } else { 1.0 };
+        let _ = writeln!(
+            out,
+            "    <line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke-width=\"{}\"/>",
+            from.x + NODE_WIDTH / 2.0,
+            from.y,
+            to.x - NODE_WIDTH / 2.0,
+            to.y,
+            stroke
+        );
+    }
+    out.push_str("  </g>\n");
+
+    out.push_str("  <g font-family=\"monospace\" font-size=\"12\" text-anchor=\"middle\">\n");
+    for node in &graph.nodes {
+        let Position { x, y } = layout.positions[&node.id];
+        let dash = if node.is_boundary {
+            " stroke-dasharray=\"4 2\""
+        } else {
+            ""
+        };
+        let _ = writeln!(
+            out,
+            "    <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" rx=\"4\" fill=\"{}\" stroke=\"#333333\"{}/>",
+            x - NODE_WIDTH / 2.0,
+            y - NODE_HEIGHT / 2.0,
+            NODE_WIDTH,
+            NODE_HEIGHT,
+            fill_color(node.color),
+            dash
+        );
+        let _ = writeln!(
+            out,
+            "    <text x=\"{}\" y=\"{}\" dominant-baseline=\"middle\">{}</text>",
+            x,
+            y,
+            short_hash(&node.id)
+        );
+    }
+    out.push_str("  </g>\n</svg>\n");
+    out
+}
+
+#[tokio::test]
+async fn test_to_svg() -> anyhow::Result<()> {
+    use crate::chain_reader::mock::MockChainReader;
+
+    let builder = MockChainReader::builder();
+    let graph = builder.generate().await?;
+    let svg = to_svg(&graph);
+
+    assert!(svg.starts_with("<svg "));
+    assert!(svg.trim_end().ends_with("</svg>"));
+    assert_eq!(svg.matches("<text ").count(), graph.nodes.len());
+    assert_eq!(svg.matches("<line ").count(), graph.edges.len());
+    Ok(())
+}
@@ -1,21 +1,520 @@
 use actix_files::Files;
-use actix_web::{web, App, HttpServer, post, Responder};
+use actix_web::body::BoxBody;
+use actix_web::dev::Service;
+use actix_web::http::header::{self, Accept, Header};
+use actix_web::middleware::Compress;
+use actix_web::{get, web, App, HttpRequest, HttpResponse, HttpServer, post, Responder};
 use anyhow::Result;
-use crate::dag_graph::DagGraphBuilder;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use starcoin_crypto::HashValue;
+use std::sync::{Arc, RwLock};
+use std::time::Instant;
+use crate::chain_reader::ext::BlockDetail;
+use crate::chain_reader::{BlockQuery, BlockWindow};
+use crate::dag_graph::layout::LayerBy;
+use crate::dag_graph::stream::SEGMENT_BLOCKS;
+use crate::dag_graph::{DagGraph, DagGraphBuilder, GraphDiff, Reachability};
+use crate::encoding::Encoding;
+use crate::metrics::Metrics;
+use crate::render::Format;
+use crate::snapshot::SnapshotStore;
+
+// Large enough for a client to post back a wide view for diffing.
+const MAX_JSON_BODY: usize = 64 * 1024 * 1024;
+
+/// Serializes the wrapped value in the first encoding of the request's `Accept`
+/// header that is supported, falling back to JSON.
+struct Negotiated<T>(T);
+
+impl<T: Serialize> Responder for Negotiated<T> {
+    type Body = BoxBody;
+
+    fn respond_to(self, req: &HttpRequest) -> HttpResponse {
+        let encoding = Accept::parse(req)
+            .ok()
+            .and_then(|accept| {
+                accept
+                    .ranked()
+                    .iter()
+                    .find_map(|mime| Encoding::from_media_type(mime.essence_str()))
+            })
+            .unwrap_or(Encoding::Json);
+        match encoding.encode(&self.0) {
+            Ok(body) => HttpResponse::Ok()
+                .content_type(encoding.content_type())
+                .insert_header((header::VARY, "Accept"))
+                .body(body),
+            Err(e) => actix_web::error::ErrorInternalServerError(e).error_response(),
+        }
+    }
+}
+
+// Largest `window` accepted by the view routes.
+const MAX_WINDOW: u64 = 10_000;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct DagViewRequest {
+    stub_edges: bool,
+    /// Number of latest selected chain blocks, overriding the server default.
+    window: Option<u64>,
+    /// Number of a past selected chain block to view the DAG as of.
+    at: Option<u64>,
+    layer_by: LayerBy,
+    /// Layer at the left margin of the layout, 0 if omitted; fix it across polls for stable positions.
+    layout_origin: Option<u64>,
+    /// Fetch block bodies for transaction counts and gas.
+    bodies: bool,
+}
+
+impl DagViewRequest {
+    fn builder(&self, base: &DagGraphBuilder) -> Result<DagGraphBuilder, actix_web::Error> {
+        if self.window.is_some_and(|n| n > MAX_WINDOW) {
+            return Err(actix_web::error::ErrorBadRequest(format!(
+                "window must be at most {}",
+                MAX_WINDOW
+            )));
+        }
+        let builder = base
+            .clone()
+            .with_stub_edges(self.stub_edges)
+            .with_layer_by(self.layer_by)
+            .with_layout_origin(self.layout_origin)
+            .with_bodies(self.bodies);
+        let builder = match self.window {
+            Some(n) => builder.with_window(BlockWindow::Latest(n)),
+            None => builder,
+        };
+        Ok(match self.at {
+            Some(number) => builder.with_tip(number),
+            None => builder,
+        })
+    }
+}
 
 #[post("/dag_view")]
-async fn dag_view_handler(builder: web::Data<DagGraphBuilder>) -> Result<impl Responder, actix_web::Error> {
-    let graph = builder.generate()
+async fn dag_view_handler(
+    builder: web::Data<DagGraphBuilder>,
+    req: Option<web::Json<DagViewRequest>>,
+) -> Result<impl Responder, actix_web::Error> {
+    let req = req.map(web::Json::into_inner).unwrap_or_default();
+    let graph = req
+        .builder(&builder)?
+        .generate()
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    Ok(Negotiated(graph))
+}
+
+/// The view as NDJSON, one node, edge or missing block per line, sent while the
+/// crawl is still running. The last line has type `end`.
+#[post("/dag_view/stream")]
+async fn dag_view_stream_handler(
+    builder: web::Data<DagGraphBuilder>,
+    req: Option<web::Json<DagViewRequest>>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let req = req.map(web::Json::into_inner).unwrap_or_default();
+    let lines = req.builder(&builder)?.stream(SEGMENT_BLOCKS).map(|item| {
+        let mut line = serde_json::to_vec(&item?)?;
+        line.push(b'\n');
+        Ok::<_, anyhow::Error>(web::Bytes::from(line))
+    });
+    Ok(HttpResponse::Ok()
+        .content_type("application/x-ndjson")
+        .streaming(lines.map(|line| line.map_err(actix_web::error::ErrorInternalServerError))))
+}
+
+#[derive(Debug, Deserialize)]
+struct DagDiffRequest {
+    base: DagGraph,
+    /// Graph to compare against; the current view if omitted.
+    target: Option<DagGraph>,
+    #[serde(flatten)]
+    view: DagViewRequest,
+}
+
+// Registered with a raised body limit in `start_server`, since clients post
+// whole views back for diffing.
+async fn dag_diff_handler(
+    builder: web::Data<DagGraphBuilder>,
+    req: web::Json<DagDiffRequest>,
+) -> Result<impl Responder, actix_web::Error> {
+    let req = req.into_inner();
+    let target = match req.target {
+        Some(target) => target,
+        None => req
+            .view
+            .builder(&builder)?
+            .generate()
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?,
+    };
+    Ok(Negotiated(GraphDiff::new(&req.base, &target)))
+}
+
+#[get("/dag_view.{format}")]
+async fn dag_view_export_handler(
+    builder: web::Data<DagGraphBuilder>,
+    format: web::Path<String>,
+    req: web::Query<DagViewRequest>,
+) -> Result<impl Responder, actix_web::Error> {
+    let format: Format = format
+        .parse()
+        .map_err(actix_web::error::ErrorNotFound)?;
+    let graph = req
+        .builder(&builder)?
+        .generate()
         .await
         .map_err(actix_web::error::ErrorInternalServerError)?;
-    Ok(web::Json(graph))
+    let body = format
+        .render(&graph)
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok()
+        .content_type(format.content_type())
+        .body(body))
+}
+
+/// Saves the view described by `view` as generated by the server, so a snapshot
+/// always records what the server saw.
+#[derive(Debug, Deserialize)]
+struct SaveSnapshotRequest {
+    name: String,
+    #[serde(flatten)]
+    view: DagViewRequest,
+}
+
+#[post("/snapshots")]
+async fn save_snapshot_handler(
+    builder: web::Data<DagGraphBuilder>,
+    store: web::Data<SnapshotStore>,
+    req: web::Json<SaveSnapshotRequest>,
+) -> Result<impl Responder, actix_web::Error> {
+    let req = req.into_inner();
+    let graph = req
+        .view
+        .builder(&builder)?
+        .generate()
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let info = store
+        .save(req.name, &graph)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .ok_or_else(|| actix_web::error::ErrorInsufficientStorage("snapshot limit reached"))?;
+    Ok(Negotiated(info))
+}
+
+#[get("/snapshots")]
+async fn list_snapshots_handler(
+    store: web::Data<SnapshotStore>,
+) -> Result<impl Responder, actix_web::Error> {
+    let infos = store
+        .list()
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    Ok(Negotiated(infos))
+}
+
+#[get("/snapshots/{id}")]
+async fn snapshot_handler(
+    store: web::Data<SnapshotStore>,
+    id: web::Path<String>,
+) -> Result<impl Responder, actix_web::Error> {
+    match store
+        .load(&id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+    {
+        Some(snapshot) => Ok(Negotiated(snapshot)),
+        None => Err(actix_web::error::ErrorNotFound(format!("no snapshot {}", id))),
+    }
+}
+
+fn parse_hash(s: &str) -> Result<HashValue, actix_web::Error> {
+    HashValue::from_hex_literal(s).map_err(actix_web::error::ErrorBadRequest)
+}
+
+// Checks that the follower has indexed every one of `ids`.
+fn check_indexed(reach: &Reachability, ids: &[HashValue]) -> Result<(), actix_web::Error> {
+    match ids.iter().find(|id| !reach.contains(id)) {
+        Some(id) => Err(actix_web::error::ErrorNotFound(format!(
+            "block {} is not indexed",
+            id.to_hex_literal()
+        ))),
+        None => Ok(()),
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct BlockDetailResponse {
+    #[serde(flatten)]
+    detail: BlockDetail,
+    /// Children of the block known to the reachability index, or in the
+    /// current view if the index does not hold it.
+    children: Vec<HashValue>,
+}
+
+#[get("/block/{hash}")]
+async fn block_detail_handler(
+    builder: web::Data<DagGraphBuilder>,
+    reachability: web::Data<RwLock<Reachability>>,
+    hash: web::Path<String>,
+) -> Result<impl Responder, actix_web::Error> {
+    let id = parse_hash(&hash)?;
+    let indexed = {
+        let reach = reachability.read().unwrap();
+        reach.contains(&id).then(|| reach.children(&id))
+    };
+    let children = async {
+        match indexed {
+            Some(children) => Ok(children),
+            None => builder.children(id).await,
+        }
+    };
+    let reader = builder.reader();
+    let (detail, mut children) = futures::try_join!(reader.block_detail(id), children)
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let detail = detail.ok_or_else(|| {
+        actix_web::error::ErrorNotFound(format!("unknown block {}", id.to_hex_literal()))
+    })?;
+    children.sort();
+    Ok(Negotiated(BlockDetailResponse { detail, children }))
+}
+
+#[get("/stats")]
+async fn stats_handler(
+    builder: web::Data<DagGraphBuilder>,
+    req: web::Query<DagViewRequest>,
+) -> Result<impl Responder, actix_web::Error> {
+    let stats = req
+        .builder(&builder)?
+        .stats()
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    Ok(Negotiated(stats))
+}
+
+/// Service and chain metrics in the Prometheus text format. The chain gauges
+/// hold the chain follower's last poll.
+#[get("/metrics")]
+async fn metrics_handler(metrics: web::Data<Metrics>) -> Result<impl Responder, actix_web::Error> {
+    let body = metrics
+        .encode()
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok()
+        .content_type("application/openmetrics-text; version=1.0.0; charset=utf-8")
+        .body(body))
+}
+
+// Largest `count` accepted by `/selected_chain`.
+const MAX_CHAIN_COUNT: u64 = 10_000;
+
+#[derive(Debug, Deserialize)]
+struct SelectedChainRequest {
+    /// First block number; the latest blocks if omitted.
+    from: Option<u64>,
+    #[serde(default = "default_chain_count")]
+    count: u64,
+}
+
+fn default_chain_count() -> u64 {
+    20
+}
+
+#[get("/selected_chain")]
+async fn selected_chain_handler(
+    builder: web::Data<DagGraphBuilder>,
+    req: web::Query<SelectedChainRequest>,
+) -> Result<impl Responder, actix_web::Error> {
+    if req.count > MAX_CHAIN_COUNT {
+        return Err(actix_web::error::ErrorBadRequest(format!(
+            "count must be at most {}",
+            MAX_CHAIN_COUNT
+        )));
+    }
+    let window = match req.from {
+        Some(from) => BlockWindow::Between {
+            from,
+            count: req.count,
+        },
+        None => BlockWindow::Latest(req.count),
+    };
+    let chain = builder
+        .reader()
+        .selected_chain(window)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    Ok(Negotiated(chain))
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchRequest {
+    q: String,
+}
+
+/// Blocks matching a number, hash or hash prefix, newest first.
+#[get("/search")]
+async fn search_handler(
+    builder: web::Data<DagGraphBuilder>,
+    req: web::Query<SearchRequest>,
+) -> Result<impl Responder, actix_web::Error> {
+    let query: BlockQuery = req.q.parse().map_err(actix_web::error::ErrorBadRequest)?;
+    let blocks = builder
+        .reader()
+        .search(&query)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    Ok(Negotiated(blocks))
+}
+
+// Largest `window` accepted by `/txn/{hash}`, which fetches a body per block.
+const MAX_TRACE_WINDOW: u64 = 1_000;
+
+/// Blocks of the view that include a transaction, in GHOSTDAG order.
+#[get("/txn/{hash}")]
+async fn txn_trace_handler(
+    builder: web::Data<DagGraphBuilder>,
+    hash: web::Path<String>,
+    req: web::Query<DagViewRequest>,
+) -> Result<impl Responder, actix_web::Error> {
+    if req.window.is_some_and(|n| n > MAX_TRACE_WINDOW) {
+        return Err(actix_web::error::ErrorBadRequest(format!(
+            "window must be at most {}",
+            MAX_TRACE_WINDOW
+        )));
+    }
+    let txn = parse_hash(&hash)?;
+    let trace = req
+        .builder(&builder)?
+        .trace_transaction(txn)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    Ok(Negotiated(trace))
+}
+
+#[derive(Debug, Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+enum Relation {
+    Past,
+    Future,
+    Anticone,
+}
+
+#[derive(Debug, Deserialize)]
+struct RelationRequest {
+    /// Blocks numbered more than this away from the block are left out.
+    #[serde(default = "default_relation_window")]
+    window: u64,
+}
+
+fn default_relation_window() -> u64 {
+    20
+}
+
+#[derive(Debug, Serialize)]
+struct RelationResponse {
+    block: HashValue,
+    blocks: Vec<HashValue>,
+}
+
+/// Past, future or anticone of an indexed block, limited to blocks numbered
+/// within `window` of it.
+#[get("/block/{hash}/{relation:past|future|anticone}")]
+async fn block_relation_handler(
+    reachability: web::Data<RwLock<Reachability>>,
+    path: web::Path<(String, Relation)>,
+    req: web::Query<RelationRequest>,
+) -> Result<impl Responder, actix_web::Error> {
+    if req.window > MAX_WINDOW {
+        return Err(actix_web::error::ErrorBadRequest(format!(
+            "window must be at most {}",
+            MAX_WINDOW
+        )));
+    }
+    let (hash, relation) = path.into_inner();
+    let id = parse_hash(&hash)?;
+    let reach = reachability.read().unwrap();
+    check_indexed(&reach, &[id])?;
+    let mut blocks: Vec<_> = match relation {
+        Relation::Past => reach.past_within(&id, req.window),
+        Relation::Future => reach.future_within(&id, req.window),
+        Relation::Anticone => reach.anticone_within(&id, req.window),
+    }
+    .into_iter()
+    .collect();
+    blocks.sort();
+    Ok(Negotiated(RelationResponse { block: id, blocks }))
+}
+
+#[derive(Debug, Serialize)]
+struct IsAncestorResponse {
+    ancestor: HashValue,
+    descendant: HashValue,
+    is_ancestor: bool,
+}
+
+#[get("/block/{hash}/is_ancestor_of/{other}")]
+async fn is_ancestor_handler(
+    reachability: web::Data<RwLock<Reachability>>,
+    path: web::Path<(String, String)>,
+) -> Result<impl Responder, actix_web::Error> {
+    let (hash, other) = path.into_inner();
+    let (ancestor, descendant) = (parse_hash(&hash)?, parse_hash(&other)?);
+    let reach = reachability.read().unwrap();
+    check_indexed(&reach, &[ancestor, descendant])?;
+    Ok(Negotiated(IsAncestorResponse {
+        ancestor,
+        descendant,
+        is_ancestor: reach.is_ancestor(&ancestor, &descendant),
+    }))
 }
 
-pub async fn start_server(builder: DagGraphBuilder,listen: &str) -> Result<()> {
+pub async fn start_server(
+    builder: DagGraphBuilder,
+    snapshots: SnapshotStore,
+    metrics: Arc<Metrics>,
+    reachability: Arc<RwLock<Reachability>>,
+    listen: &str,
+) -> Result<()> {
     HttpServer::new(move || {
+        let request_metrics = metrics.clone();
         App::new()
             .app_data(web::Data::new(builder.clone()))
+            .app_data(web::Data::new(snapshots.clone()))
+            .app_data(web::Data::from(metrics.clone()))
+            .app_data(web::Data::from(reachability.clone()))
+            .wrap(Compress::default())
+            .wrap_fn(move |req, srv| {
+                let start = Instant::now();
+                let route = req.match_pattern().unwrap_or_else(|| "unmatched".to_string());
+                let metrics = request_metrics.clone();
+                let response = srv.call(req);
+                async move {
+                    let response = response.await;
+                    metrics.observe_request(&route, start.elapsed());
+                    response
+                }
+            })
             .service(dag_view_handler)
+            .service(dag_view_stream_handler)
+            .service(
+                web::resource("/dag_diff")
+                    .app_data(web::JsonConfig::default().limit(MAX_JSON_BODY))
+                    .route(web::post().to(dag_diff_handler)),
+            )
+            .service(dag_view_export_handler)
+            .service(stats_handler)
+            .service(metrics_handler)
+            .service(selected_chain_handler)
+            .service(search_handler)
+            .service(block_detail_handler)
+            .service(block_relation_handler)
+            .service(txn_trace_handler)
+            .service(is_ancestor_handler)
+            .service(save_snapshot_handler)
+            .service(list_snapshots_handler)
+            .service(snapshot_handler)
             .service(Files::new("/", "./static").index_file("index.html"))
     })
     .bind(&listen)?
@@ -0,0 +1,203 @@
+//! Named DAG views saved on disk, so they can be shared after the live view has moved on.
+
+use crate::dag_graph::DagGraph;
+use serde::{Deserialize, Serialize};
+use starcoin_crypto::HashValue;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::Mutex;
+
+// Hex digits in a snapshot id.
+const ID_LEN: usize = 16;
+
+/// Snapshots a store keeps unless told otherwise.
+pub const DEFAULT_MAX_SNAPSHOTS: usize = 1_000;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SnapshotInfo {
+    pub id: String,
+    pub name: String,
+    /// Milliseconds since the Unix epoch.
+    pub created_at: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Snapshot {
+    #[serde(flatten)]
+    pub info: SnapshotInfo,
+    pub graph: DagGraph,
+}
+
+/// Snapshots kept as files in one directory: `{id}.json` holds the info and
+/// `{id}.graph.json` the graph. Snapshots are never modified once saved, and
+/// saving stops once `max_snapshots` are kept.
+#[derive(Clone, Debug)]
+pub struct SnapshotStore {
+    dir: PathBuf,
+    max_snapshots: usize,
+    // Snapshots in `dir`, counted by the first save. Locked for the whole of a
+    // save, so concurrent saves cannot pass the limit together.
+    count: Arc<Mutex<Option<usize>>>,
+}
+
+impl SnapshotStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            max_snapshots: DEFAULT_MAX_SNAPSHOTS,
+            count: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub fn with_max_snapshots(mut self, max_snapshots: usize) -> Self {
+        self.max_snapshots = max_snapshots;
+        self
+    }
+
+    /// Saves `graph` as a new snapshot, or returns `None` if the store already
+    /// keeps `max_snapshots`.
+    pub async fn save(
+        &self,
+        name: String,
+        graph: &DagGraph,
+    ) -> anyhow::Result<Option<SnapshotInfo>> {
+        let mut count = self.count.lock().await;
+        let saved = match *count {
+            Some(saved) => saved,
+            None => self.saved_ids().await?.len(),
+        };
+        *count = Some(saved);
+        if saved >= self.max_snapshots {
+            return Ok(None);
+        }
+        tokio::fs::create_dir_all(&self.dir).await?;
+        let id = loop {
+            let id = HashValue::random().to_hex()[..ID_LEN].to_string();
+            if !tokio::fs::try_exists(self.info_path(&id)).await? {
+                break id;
+            }
+        };
+        let info = SnapshotInfo {
+            id,
+            name,
+            created_at: SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as u64,
+        };
+        // The graph goes first so a listed snapshot always has one.
+        write_atomic(&self.graph_path(&info.id), &serde_json::to_vec(graph)?).await?;
+        write_atomic(&self.info_path(&info.id), &serde_json::to_vec(&info)?).await?;
+        *count = Some(saved + 1);
+        Ok(Some(info))
+    }
+
+    /// The snapshot with `id`, or `None` if there is none.
+    pub async fn load(&self, id: &str) -> anyhow::Result<Option<Snapshot>> {
+        if !is_valid_id(id) || !tokio::fs::try_exists(self.info_path(id)).await? {
+            return Ok(None);
+        }
+        let info = serde_json::from_slice(&tokio::fs::read(self.info_path(id)).await?)?;
+        let graph = serde_json::from_slice(&tokio::fs::read(self.graph_path(id)).await?)?;
+        Ok(Some(Snapshot { info, graph }))
+    }
+
+    /// Every saved snapshot, oldest first.
+    pub async fn list(&self) -> anyhow::Result<Vec<SnapshotInfo>> {
+        let mut infos = Vec::new();
+        for id in self.saved_ids().await? {
+            let info: SnapshotInfo = serde_json::from_slice(&tokio::fs::read(self.info_path(&id)).await?)?;
+            infos.push(info);
+        }
+        infos.sort_by(|a, b| (a.created_at, &a.id).cmp(&(b.created_at, &b.id)));
+        Ok(infos)
+    }
+
+    // Ids of the info files in `dir`, without reading them.
+    async fn saved_ids(&self) -> anyhow::Result<Vec<String>> {
+        let mut ids = Vec::new();
+        let mut entries = match tokio::fs::read_dir(&self.dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(ids),
+            Err(e) => return Err(e.into()),
+        };
+        while let Some(entry) = entries.next_entry().await? {
+            let file_name = entry.file_name();
+            let Some(id) = file_name.to_str().and_then(|f| f.strip_suffix(".json")) else {
+                continue;
+            };
+            if is_valid_id(id) {
+                ids.push(id.to_string());
+            }
+        }
+        Ok(ids)
+    }
+
+    fn info_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+
+    fn graph_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.graph.json", id))
+    }
+}
+
+// Ids come from request paths, so only accept what `save` generates.
+fn is_valid_id(id: &str) -> bool {
+    id.len() == ID_LEN && id.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+async fn write_atomic(path: &Path, contents: &[u8]) -> anyhow::Result<()> {
+    let tmp = path.with_extension("tmp");
+    tokio::fs::write(&tmp, contents).await?;
+    tokio::fs::rename(&tmp, path).await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_snapshot_round_trip() -> anyhow::Result<()> {
+    use crate::chain_reader::mock::MockChainReader;
+
+    let dir = std::env::temp_dir().join(format!("graphd-snapshots-{}", HashValue::random().to_hex()));
+    let store = SnapshotStore::new(&dir).with_max_snapshots(1);
+    assert!(store.list().await?.is_empty());
+
+    let builder = MockChainReader::builder();
+    let graph = builder.generate().await?;
+    let info = store
+        .save("weird merge".to_string(), &graph)
+        .await?
+        .expect("the store has room");
+
+    let listed = store.list().await?;
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0].id, info.id);
+    let snapshot = store.load(&info.id).await?.expect("snapshot was saved");
+    assert_eq!(snapshot.info.name, "weird merge");
+    assert_eq!(snapshot.graph.nodes.len(), graph.nodes.len());
+    assert!(store.load("../../etc/passwd").await?.is_none());
+    assert!(store.save("one too many".to_string(), &graph).await?.is_none());
+
+    tokio::fs::remove_dir_all(&dir).await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_concurrent_saves_respect_the_limit() -> anyhow::Result<()> {
+    use crate::chain_reader::mock::MockChainReader;
+
+    let dir = std::env::temp_dir().join(format!("graphd-snapshots-{}", HashValue::random().to_hex()));
+    let store = SnapshotStore::new(&dir).with_max_snapshots(3);
+    let builder = MockChainReader::builder();
+    let graph = builder.generate().await?;
+
+    let saves = (0..8).map(|i| store.save(format!("view {}", i), &graph));
+    let saved = futures::future::try_join_all(saves).await?;
+    assert_eq!(saved.iter().flatten().count(), 3);
+    assert_eq!(store.list().await?.len(), 3);
+
+    // A fresh store over the same directory counts what is already there.
+    let reopened = SnapshotStore::new(&dir).with_max_snapshots(3);
+    assert!(reopened.save("late".to_string(), &graph).await?.is_none());
+
+    tokio::fs::remove_dir_all(&dir).await?;
+    Ok(())
+}